@@ -5,10 +5,11 @@ use egui::Layout;
 use egui_extras::{Column, TableBuilder};
 use egui_extras::{Size, StripBuilder};
 
-use rand::seq::{IndexedRandom as _, index};
-use rand::{SeedableRng as _, rngs::StdRng};
-
-use crate::class_room::{AssignResult, BuilderData, Gender, Student, StudentId};
+use crate::class_room::{
+    AnnealingConfig, AssignResult, BuilderData, COST_TERMS, ClassConfig, CoolingSchedule,
+    CostWeights, GeneticConfig, MoveConfig, Progress, RelationKind, Relationship, SeparationGroup,
+    SolverKind, StopConfig, Student, StudentId, TabuConfig, TeacherConflict, Violation, solve,
+};
 use crate::spawn_async;
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -23,7 +24,6 @@ pub struct MainApp {
 
     app_mode: AppPage,
 
-    #[serde(skip)]
     builder_data: BuilderData,
 
     #[serde(skip)]
@@ -34,13 +34,25 @@ pub struct MainApp {
 
     #[serde(skip)]
     cancellation_token: Option<Arc<AtomicBool>>, // 여기에 저장
+
+    #[serde(skip)]
+    solve_error: Option<String>,
+    #[serde(skip)]
+    solve_note: Option<String>,
+
+    /// confidential separations are hidden unless revealed (never persisted)
+    #[serde(skip)]
+    reveal_confidential: bool,
+    #[serde(skip)]
+    export_confidential: bool,
 }
 
 #[derive(Clone, PartialEq)]
 pub(crate) enum AppState {
     Ready,
-    InProgress(usize, usize, f32), // step, total_step, cost
+    InProgress(Progress),
     Done(AssignResult),
+    Failed(String),
 }
 
 impl Default for MainApp {
@@ -54,6 +66,10 @@ impl Default for MainApp {
             app_state: AppState::Ready,
             rx_app_state: None,
             cancellation_token: None,
+            solve_error: None,
+            solve_note: None,
+            reveal_confidential: false,
+            export_confidential: false,
 
             builder_data: BuilderData::new_demo(),
         }
@@ -68,11 +84,13 @@ impl MainApp {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
+        let mut app: Self = if let Some(storage) = cc.storage {
             eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
         } else {
             Default::default()
-        }
+        };
+        app.builder_data.resize_class_configs();
+        app
     }
 
     fn ui_data_input(&mut self, ui: &mut egui::Ui) {
@@ -81,9 +99,11 @@ impl MainApp {
         ui.add_space(10.0);
 
         StripBuilder::new(ui)
-            .size(Size::relative(0.5).at_least(100.0))
+            .size(Size::relative(0.45).at_least(100.0))
+            .size(Size::exact(1.0)) // [핵심] 구분선용 1px 셀
+            .size(Size::relative(0.12).at_least(60.0))
             .size(Size::exact(1.0)) // [핵심] 구분선용 1px 셀
-            .size(Size::relative(0.25).at_least(60.0))
+            .size(Size::relative(0.12).at_least(60.0))
             .size(Size::exact(1.0)) // [핵심] 구분선용 1px 셀
             .size(Size::remainder())
             .horizontal(|mut strip| {
@@ -94,7 +114,11 @@ impl MainApp {
                     });
                     ui.add_space(10.0);
                     ui.add_space(10.0);
-                    ui_student_table(ui, &mut self.builder_data.students);
+                    ui_student_table(
+                        ui,
+                        &mut self.builder_data.students,
+                        self.builder_data.n_class,
+                    );
                 });
                 // separator
                 strip.cell(|ui| {
@@ -108,7 +132,13 @@ impl MainApp {
                 strip.cell(|ui| {
                     ui.strong("Dislike Group");
                     ui.add_space(10.0);
-                    ui_dislike_group(ui, &mut self.builder_data.dislike_group);
+                    ui.checkbox(&mut self.reveal_confidential, "Reveal confidential");
+                    ui.add_space(10.0);
+                    ui_dislike_group(
+                        ui,
+                        &mut self.builder_data.dislike_group,
+                        self.reveal_confidential,
+                    );
                 });
                 // separator
                 strip.cell(|ui| {
@@ -124,6 +154,28 @@ impl MainApp {
                     ui.add_space(10.0);
                     ui_like_group(ui, &mut self.builder_data.like_group);
                 });
+                // separator
+                strip.cell(|ui| {
+                    let rect = ui.available_rect_before_wrap();
+                    ui.painter().rect_filled(
+                        rect,
+                        0.0,
+                        ui.visuals().widgets.noninteractive.bg_stroke.color,
+                    );
+                });
+                strip.cell(|ui| {
+                    ui.strong("Pairwise Relationships");
+                    ui.add_space(10.0);
+                    let n_students = self.builder_data.students.len();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui_relationships(
+                            ui,
+                            &mut self.builder_data.relationships,
+                            n_students,
+                            self.reveal_confidential,
+                        );
+                    });
+                });
             });
 
         // ui.horizontal(|ui| {
@@ -161,6 +213,7 @@ impl MainApp {
                         )
                         .changed()
                     {
+                        self.builder_data.resize_class_configs();
                         self.builder_data.init();
                     }
 
@@ -171,6 +224,26 @@ impl MainApp {
                             .logarithmic(true)
                             .text("# iteration"),
                     );
+
+                    ui.add_space(10.0);
+
+                    ui.horizontal(|ui| {
+                        let mut changed = ui
+                            .add(egui::DragValue::new(&mut self.builder_data.seed))
+                            .on_hover_text(
+                                "the starting assignment and every run only depend on the seed, \
+                                 the data and the settings: the same seed gives the same result",
+                            )
+                            .changed();
+                        if ui.button("New seed").clicked() {
+                            self.builder_data.seed = u64::from(rand::random::<u32>());
+                            changed = true;
+                        }
+                        ui.label("seed");
+                        if changed {
+                            self.builder_data.init();
+                        }
+                    });
                 });
             });
 
@@ -188,40 +261,93 @@ impl MainApp {
 
             ui.add_space(20.0);
 
+            ui.add_enabled_ui(matches!(self.app_state, AppState::Ready), |ui| {
+                egui::ComboBox::from_id_salt("solver")
+                    .selected_text(self.builder_data.solver.as_str())
+                    .show_ui(ui, |ui| {
+                        for kind in SolverKind::ALL {
+                            ui.selectable_value(&mut self.builder_data.solver, kind, kind.as_str());
+                        }
+                    });
+            });
             if ui
                 .add_enabled_ui(matches!(self.app_state, AppState::Ready), |ui| {
-                    ui.add_sized([100.0, 30.0], egui::Button::new("Do Naive Shuffle"))
+                    ui.add_sized([100.0, 30.0], egui::Button::new("Solve"))
                 })
                 .inner
                 .clicked()
                 && self.builder_data.assign_result.is_some()
+                && self.check_solvable()
             {
-                self.spawn_method_naive_shuffle(ui);
+                let (kind, note) = self.builder_data.solver.resolve(&self.builder_data);
+                self.solve_note = note;
+                self.spawn_solver(kind, ui);
             }
+        });
 
-            if ui
-                .add_enabled_ui(matches!(self.app_state, AppState::Ready), |ui| {
-                    ui.add_sized([100.0, 30.0], egui::Button::new("Do Annealing"))
-                })
-                .inner
-                .clicked()
-                && self.builder_data.assign_result.is_some()
-            {
-                self.spawn_method_annealing(ui);
-            }
+        ui.add_space(10.0);
 
-            if ui
-                // .add_enabled_ui(matches!(self.app_state, AppState::Ready))
-                .add_enabled_ui(false, |ui| {
-                    ui.add_sized([100.0, 30.0], egui::Button::new("Do Genetic"))
-                })
-                .inner
-                .clicked()
-                && self.builder_data.assign_result.is_some()
-            {
-                // self.spawn_method_genetic(ui);
+        if let Some(error) = &self.solve_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            ui.add_space(10.0);
+        }
+        if let Some(note) = &self.solve_note {
+            ui.label(note);
+            ui.add_space(10.0);
+        }
+
+        egui::CollapsingHeader::new("Class Setup").show(ui, |ui| {
+            ui_class_configs(ui, &mut self.builder_data.class_configs);
+        });
+        egui::CollapsingHeader::new("Cost Weights").show(ui, |ui| {
+            if ui_cost_weights(ui, &mut self.builder_data.cost_weights) {
+                self.builder_data.refresh_cost();
             }
         });
+        egui::CollapsingHeader::new("Annealing").show(ui, |ui| {
+            ui_annealing_config(ui, &mut self.builder_data.annealing);
+        });
+        egui::CollapsingHeader::new("Stopping").show(ui, |ui| {
+            ui_stop_config(ui, &mut self.builder_data.stop);
+        });
+        egui::CollapsingHeader::new("Moves").show(ui, |ui| {
+            ui.label("relative frequency of each move type, in annealing and tabu search");
+            ui_move_config(ui, &mut self.builder_data.moves);
+        });
+        egui::CollapsingHeader::new("Tabu Search").show(ui, |ui| {
+            ui_tabu_config(ui, &mut self.builder_data.tabu);
+        });
+        egui::CollapsingHeader::new("Genetic").show(ui, |ui| {
+            ui_genetic_config(ui, &mut self.builder_data.genetic);
+        });
+        egui::CollapsingHeader::new("Exact (ILP)").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Max. variables")
+                    .on_hover_text("students × classes; above this a heuristic runs instead");
+                ui.add(
+                    egui::DragValue::new(&mut self.builder_data.exact.max_variables)
+                        .range(1..=10_000),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Gap tolerance").on_hover_text(
+                    "relative gap to the lower bound at which the search stops, 0 to prove optimality",
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.builder_data.exact.gap_tolerance)
+                        .range(0.0..=0.5)
+                        .speed(0.0001),
+                );
+            });
+        });
+        egui::CollapsingHeader::new("Teacher Conflicts").show(ui, |ui| {
+            ui_teacher_conflicts(
+                ui,
+                &mut self.builder_data.teacher_conflicts,
+                &self.builder_data.class_configs,
+                self.builder_data.students.len(),
+            );
+        });
 
         ui.add_space(10.0);
 
@@ -229,22 +355,35 @@ impl MainApp {
             while let Ok(state) = rx.try_recv() {
                 self.app_state = match &state {
                     AppState::Ready => state,
-                    AppState::InProgress(_, _, _) => state,
+                    AppState::InProgress(_) => state,
                     AppState::Done(assign) => {
                         self.builder_data.assign_result = Some(assign.clone());
                         AppState::Ready
                     }
+                    AppState::Failed(error) => {
+                        self.solve_error = Some(error.clone());
+                        AppState::Ready
+                    }
                 };
                 ui.ctx().request_repaint();
             }
         }
-        if let AppState::InProgress(i, n, cost) = &self.app_state {
+        if let AppState::InProgress(progress) = &self.app_state {
+            let Progress {
+                step: i,
+                total_step: n,
+                fraction,
+                cost,
+                temperature,
+                elapsed,
+                rate,
+                eta,
+            } = progress;
             ui.add_space(10.0);
             ui.horizontal(|ui| {
-                let progress = *i as f32 / *n as f32;
                 ui.add_sized(
                     (300.0, 20.0),
-                    egui::ProgressBar::new(progress).show_percentage(), // .animate(true),
+                    egui::ProgressBar::new(*fraction).show_percentage(), // .animate(true),
                 )
                 .on_hover_text(format!("{i}/{n}"));
                 if ui
@@ -255,10 +394,24 @@ impl MainApp {
                         cancel_flag.store(true, std::sync::atomic::Ordering::Relaxed);
                     }
                 }
+                if let Some(temperature) = temperature {
+                    ui.label("T:");
+                    ui.strong(format!("{temperature:.2}"));
+                }
             });
             ui.horizontal(|ui| {
                 ui.label("cost:");
                 ui.strong(format!("{cost:.1}"));
+                ui.label("elapsed:");
+                ui.strong(format!("{elapsed:.1} s"));
+                ui.label("speed:");
+                ui.strong(format!("{rate:.0} it/s"));
+                if let Some(eta) = eta {
+                    ui.label("ETA:");
+                    ui.strong(format!("{eta:.1} s")).on_hover_text(
+                        "at most: no improvement or the target cost may stop the run earlier",
+                    );
+                }
             });
             ui.add_space(10.0);
         }
@@ -273,24 +426,65 @@ impl MainApp {
             ui.strong("Statistics:");
             ui.add_space(10.0);
 
-            assign.ui_statistics(ui);
+            assign.ui_statistics(ui, &self.builder_data);
+
+            ui.add_space(10.0);
+            ui_violations(
+                ui,
+                &assign.violations(&self.builder_data),
+                &self.builder_data,
+            );
 
             ui.add_space(10.0);
             ui.separator();
 
-            ui.strong("Class & Students:");
+            ui.horizontal(|ui| {
+                ui.strong("Class & Students:");
+                if ui.button("Copy roster (csv)").clicked() {
+                    ui.ctx()
+                        .copy_text(assign.roster_csv(&self.builder_data, self.export_confidential));
+                }
+                ui.checkbox(&mut self.export_confidential, "incl. confidential")
+                    .on_hover_text("include confidential separations in the export");
+            });
             ui.add_space(10.0);
             egui::ScrollArea::vertical().show(ui, |ui| {
                 for class in &assign.rooms {
-                    class.ui_layout(ui, &self.builder_data.students);
+                    class.ui_layout(
+                        ui,
+                        &self.builder_data.students,
+                        self.builder_data.class_config(class.number),
+                    );
                 }
             });
         }
     }
 
-    fn spawn_method_naive_shuffle(&mut self, ui: &mut egui::Ui) {
-        //
-        self.app_state = AppState::InProgress(0, self.builder_data.n_iteration, 0.0);
+    /// check the hard constraints; keeps the explanation for the ui if they can not be met
+    fn check_solvable(&mut self) -> bool {
+        match self.builder_data.check_feasibility() {
+            Ok(()) => {
+                self.solve_error = None;
+                self.solve_note = None;
+                true
+            }
+            Err(problems) => {
+                let lines = problems
+                    .iter()
+                    .map(|p| format!("  - {p}"))
+                    .collect::<Vec<_>>();
+                self.solve_error = Some(format!(
+                    "Can not solve, the constraints are unsatisfiable:\n{}",
+                    lines.join("\n")
+                ));
+                false
+            }
+        }
+    }
+
+    /// runs `kind` in the background, see [`solve`]
+    fn spawn_solver(&mut self, kind: SolverKind, ui: &mut egui::Ui) {
+        self.app_state = AppState::InProgress(Progress::new(0, self.builder_data.n_iteration, 0.0));
 
         let (tx, rx) = flume::unbounded();
         self.rx_app_state = Some(rx);
@@ -299,160 +493,480 @@ impl MainApp {
         self.cancellation_token = Some(cancel_flag.clone());
 
         spawn_async({
-            let mut builder_new = self.builder_data.clone();
+            let builder_new = self.builder_data.clone();
             let ctx = ui.ctx().clone();
-            let n_loop = self.builder_data.n_iteration;
-            let n_step = n_loop / 100;
             async move {
-                let Some(mut best) = builder_new.assign_result.take() else {
-                    return;
-                };
-                for k in 0..n_loop {
-                    builder_new.init();
-                    let Some(new) = builder_new.assign_result.take() else {
-                        return;
-                    };
-                    if new.overall_cost < best.overall_cost {
-                        best = new;
-                    }
-                    if k % n_step == 0 {
-                        let _ = tx
-                            .send_async(AppState::InProgress(k, n_loop, best.overall_cost.unwrap()))
-                            .await;
+                let result = solve(
+                    &builder_new,
+                    kind,
+                    builder_new.seed,
+                    &builder_new.stop,
+                    |progress| {
+                        let _ = tx.send(AppState::InProgress(progress));
                         ctx.request_repaint();
+                        !cancel_flag.load(std::sync::atomic::Ordering::Relaxed)
+                    },
+                )
+                .await;
+                let state = match result {
+                    Ok(best) => AppState::Done(best),
+                    Err(error) => AppState::Failed(error),
+                };
+                let _ = tx.send_async(state).await;
+                ctx.request_repaint();
+            }
+        });
+    }
+}
 
-                        if cancel_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                            break;
-                        }
+fn ui_class_configs(ui: &mut egui::Ui, class_configs: &mut [ClassConfig]) {
+    egui::Grid::new("class configs")
+        .num_columns(7)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("# Class");
+            ui.strong("Name");
+            ui.strong("Teacher");
+            ui.strong("Room");
+            ui.strong("Notes");
+            ui.strong("Min. seats");
+            ui.strong("Max. seats");
+            ui.end_row();
+            for (class_id, config) in class_configs.iter_mut().enumerate() {
+                ui.label(format!("No.{class_id}"));
+                ui_optional_text(ui, &mut config.name);
+                ui_optional_text(ui, &mut config.teacher);
+                ui_optional_text(ui, &mut config.location);
+                ui_optional_text(ui, &mut config.notes);
+                ui_optional_capacity(ui, &mut config.capacity_min);
+                ui_optional_capacity(ui, &mut config.capacity_max);
+                ui.end_row();
+            }
+        });
+}
 
-                        #[cfg(target_arch = "wasm32")]
-                        {
-                            use gloo_timers::future::TimeoutFuture;
-                            TimeoutFuture::new(0).await;
+/// hard and soft violations of the current assignment, by class
+fn ui_violations(ui: &mut egui::Ui, violations: &[Violation], bdata: &BuilderData) {
+    let n_hard = violations.iter().filter(|v| v.hard).count();
+    egui::CollapsingHeader::new(format!(
+        "Violation report: {n_hard} hard, {} soft",
+        violations.len() - n_hard
+    ))
+    .id_salt("violation report")
+    .show(ui, |ui| {
+        egui::ScrollArea::vertical()
+            .id_salt("violations")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("violations")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for violation in violations {
+                            if violation.hard {
+                                ui.colored_label(ui.visuals().error_fg_color, "hard");
+                            } else {
+                                ui.label("soft");
+                            }
+                            ui.label(
+                                bdata
+                                    .class_config(violation.class_id)
+                                    .display_name(violation.class_id),
+                            );
+                            ui.label(&violation.rule);
+                            let students = violation
+                                .students
+                                .iter()
+                                .map(|iid| iid.to_string())
+                                .collect::<Vec<_>>();
+                            ui.label(students.join(", "));
+                            ui.end_row();
                         }
-                    }
-                }
-                let _ = tx.send_async(AppState::Done(best)).await;
-                ctx.request_repaint();
+                    });
+            });
+    });
+}
+
+/// returns true if a weight changed
+fn ui_cost_weights(ui: &mut egui::Ui, weights: &mut CostWeights) -> bool {
+    let mut changed = false;
+    egui::Grid::new("cost weights")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for term in COST_TERMS {
+                ui.label(term.name()).on_hover_text(term.description());
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(term.weight_mut(weights))
+                            .range(0.0..=100_000.0)
+                            .speed(0.1),
+                    )
+                    .changed();
+                ui.end_row();
             }
+            ui.label("Gender tolerance").on_hover_text(
+                "deviation of the male ratio from the grade ratio that costs nothing",
+            );
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut weights.gender_tolerance, 0.0..=0.5)
+                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("Score tiers")
+                .on_hover_text("number of score quantiles, e.g. 3 = top/middle/bottom thirds");
+            changed |= ui
+                .add(egui::DragValue::new(&mut weights.score_tiers).range(2..=10))
+                .changed();
+            ui.end_row();
         });
+    if ui.button("Reset to defaults").clicked() {
+        *weights = CostWeights::default();
+        changed = true;
     }
+    changed
+}
 
-    fn spawn_method_annealing(&mut self, ui: &mut egui::Ui) {
-        //
-        self.app_state = AppState::InProgress(0, self.builder_data.n_iteration, 0.0);
+fn ui_annealing_config(ui: &mut egui::Ui, config: &mut AnnealingConfig) {
+    egui::Grid::new("annealing config")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Start temperature")
+                .on_hover_text("a worsening by this much cost is accepted with p = 1/e at the start");
+            ui.add(
+                egui::DragValue::new(&mut config.start_temperature)
+                    .range(0.001..=1_000_000.0)
+                    .speed(1.0),
+            );
+            ui.end_row();
+            ui.label("End temperature");
+            ui.add(
+                egui::DragValue::new(&mut config.end_temperature)
+                    .range(0.001..=1_000_000.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+            ui.label("Cooling schedule");
+            egui::ComboBox::from_id_salt("cooling schedule")
+                .selected_text(config.schedule.as_str())
+                .show_ui(ui, |ui| {
+                    for schedule in CoolingSchedule::ALL {
+                        ui.selectable_value(&mut config.schedule, schedule, schedule.as_str());
+                    }
+                });
+            ui.end_row();
+            ui.label("Target acceptance").on_hover_text(
+                "adaptive schedule: acceptance ratio of worsening moves at the start, going down to 0",
+            );
+            ui.add_enabled(
+                config.schedule == CoolingSchedule::Adaptive,
+                egui::Slider::new(&mut config.target_acceptance, 0.0..=1.0),
+            );
+            ui.end_row();
+            ui.label("Reheat after")
+                .on_hover_text("iterations without a new best (0 = never)");
+            ui.add(egui::DragValue::new(&mut config.reheat_after).speed(100.0));
+            ui.end_row();
+            ui.label("Reheat to")
+                .on_hover_text("fraction of the start temperature");
+            ui.add(
+                egui::Slider::new(&mut config.reheat_ratio, 0.0..=1.0)
+                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+            );
+            ui.end_row();
+        });
+    if ui.button("Reset to defaults").clicked() {
+        *config = AnnealingConfig::default();
+    }
+}
 
-        let (tx, rx) = flume::unbounded();
-        self.rx_app_state = Some(rx);
+fn ui_tabu_config(ui: &mut egui::Ui, config: &mut TabuConfig) {
+    egui::Grid::new("tabu config")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Neighbourhood")
+                .on_hover_text("random candidate moves evaluated per iteration");
+            ui.add(egui::DragValue::new(&mut config.neighbourhood).range(1..=10_000));
+            ui.end_row();
+            ui.label("Tenure")
+                .on_hover_text("iterations a moved student may not move again");
+            ui.add(egui::DragValue::new(&mut config.tenure).range(0..=10_000));
+            ui.end_row();
+        });
+    if ui.button("Reset to defaults").clicked() {
+        *config = TabuConfig::default();
+    }
+}
 
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        self.cancellation_token = Some(cancel_flag.clone());
+fn ui_stop_config(ui: &mut egui::Ui, config: &mut StopConfig) {
+    ui.label("besides # iteration, a run stops at the first of these that is met");
+    egui::Grid::new("stop config")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Time limit (s)").on_hover_text(
+                "wall-clock budget: the result then also depends on the machine speed, not only the seed",
+            );
+            ui_optional_value(ui, &mut config.time_limit, 10.0, 1.0..=3600.0);
+            ui.end_row();
+            ui.label("No improvement")
+                .on_hover_text("iterations without a new best");
+            ui_optional_value(ui, &mut config.no_improvement, 10_000, 1..=10_000_000);
+            ui.end_row();
+            ui.label("Target cost")
+                .on_hover_text("soft cost to reach without hard violations");
+            ui_optional_value(ui, &mut config.target_cost, 100.0, 0.0..=1e9);
+            ui.end_row();
+        });
+}
 
-        spawn_async({
-            let mut builder_new = self.builder_data.clone();
+fn ui_optional_value<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut Option<T>,
+    default: T,
+    range: std::ops::RangeInclusive<T>,
+) {
+    ui.horizontal(|ui| {
+        let mut checked = value.is_some();
+        if ui.checkbox(&mut checked, "").changed() {
+            *value = checked.then_some(default);
+        }
+        if let Some(value) = value.as_mut() {
+            ui.add(egui::DragValue::new(value).range(range));
+        }
+    });
+}
 
-            let average_score = builder_new
-                .students
-                .iter()
-                .fold(0.0, |prev, st| prev + st.score)
-                / builder_new.students.len() as f32;
+fn ui_move_config(ui: &mut egui::Ui, config: &mut MoveConfig) {
+    egui::Grid::new("move config")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let total = config.single + config.swap + config.rotation + config.group;
+            let share = |weight: f32| {
+                if total > 0.0 {
+                    format!("{:.0}%", weight / total * 100.0)
+                } else {
+                    "-".to_owned()
+                }
+            };
+            let moves = [
+                (
+                    "Single",
+                    "one student to another class",
+                    share(config.single),
+                ),
+                (
+                    "Swap",
+                    "two students of the same gender exchange classes",
+                    share(config.swap),
+                ),
+                (
+                    "Rotation",
+                    "three students of the same gender rotate through three classes",
+                    share(config.rotation),
+                ),
+                (
+                    "Like group",
+                    "a whole like group into the class of one of its members",
+                    share(config.group),
+                ),
+            ];
+            let weights = [
+                &mut config.single,
+                &mut config.swap,
+                &mut config.rotation,
+                &mut config.group,
+            ];
+            for ((name, hover, share), weight) in moves.into_iter().zip(weights) {
+                ui.label(name).on_hover_text(hover);
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(weight).speed(0.01).range(0.0..=1.0));
+                    ui.label(share);
+                });
+                ui.end_row();
+            }
+        });
+    if ui.button("Reset to defaults").clicked() {
+        *config = MoveConfig::default();
+    }
+}
 
-            let ctx = ui.ctx().clone();
-            let n_loop = self.builder_data.n_iteration;
-            let n_step = n_loop / 100;
-            async move {
-                //
-                let mut rng = StdRng::from_os_rng();
-                let class_ids = (0..builder_new.n_class).collect::<Vec<_>>();
-                let genders = [Gender::Male, Gender::Female];
+fn ui_genetic_config(ui: &mut egui::Ui, config: &mut GeneticConfig) {
+    egui::Grid::new("genetic config")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Population");
+            ui.add(egui::DragValue::new(&mut config.population).range(2..=1000));
+            ui.end_row();
+            ui.label("Elite")
+                .on_hover_text("best individuals copied unchanged into the next generation");
+            ui.add(
+                egui::DragValue::new(&mut config.elite)
+                    .range(1..=config.population.saturating_sub(1)),
+            );
+            ui.end_row();
+            ui.label("Tournament")
+                .on_hover_text("individuals compared to select one parent");
+            ui.add(egui::DragValue::new(&mut config.tournament).range(1..=config.population));
+            ui.end_row();
+            ui.label("Mutation swaps")
+                .on_hover_text("random swaps applied to each child");
+            ui.add(egui::DragValue::new(&mut config.mutation_swaps).range(0..=100));
+            ui.end_row();
+        });
+    if ui.button("Reset to defaults").clicked() {
+        *config = GeneticConfig::default();
+    }
+}
 
-                let Some(mut best) = builder_new.assign_result.take() else {
-                    return;
-                };
+fn ui_teacher_conflicts(
+    ui: &mut egui::Ui,
+    conflicts: &mut Vec<TeacherConflict>,
+    class_configs: &[ClassConfig],
+    n_students: usize,
+) {
+    let teachers = class_configs
+        .iter()
+        .filter_map(|config| config.teacher.clone())
+        .filter(|teacher| !teacher.is_empty())
+        .collect::<Vec<_>>();
+    if teachers.is_empty() {
+        ui.label("Set homeroom teachers in the class setup first.");
+        return;
+    }
 
-                for k in 0..n_loop {
-                    let mut new = best.clone();
-                    // select two
-
-                    let sel: Vec<_> = class_ids.choose_multiple(&mut rng, 2).copied().collect();
-                    let room_id01 = [sel[0] as usize, sel[1] as usize];
-                    let Ok([room0, room1]) = new.rooms.get_disjoint_mut(room_id01) else {
-                        break;
-                    };
-                    match genders.choose(&mut rng).unwrap_or(&Gender::Male) {
-                        Gender::Male => {
-                            let n_max = room0.students_male.len().min(room0.students_male.len());
-                            if n_max == 0 {
-                                continue;
-                            }
-                            let n_shuffle = (n_max / 10).max(1);
-
-                            let inds0 =
-                                index::sample(&mut rng, room0.students_male.len(), n_shuffle);
-                            let inds1 =
-                                index::sample(&mut rng, room1.students_male.len(), n_shuffle);
-
-                            inds0.iter().zip(inds1.iter()).for_each(|(ind0, ind1)| {
-                                // swap
-                                std::mem::swap(
-                                    &mut room0.students_male[ind0],
-                                    &mut room1.students_male[ind1],
-                                );
-                            });
-                        }
-                        Gender::Female => {
-                            let n_max =
-                                room0.students_female.len().min(room0.students_female.len());
-                            if n_max == 0 {
-                                continue;
-                            }
-                            let n_shuffle = (n_max / 10).max(1);
-
-                            let inds0 =
-                                index::sample(&mut rng, room0.students_female.len(), n_shuffle);
-                            let inds1 =
-                                index::sample(&mut rng, room1.students_female.len(), n_shuffle);
-
-                            inds0.iter().zip(inds1.iter()).for_each(|(ind0, ind1)| {
-                                // swap
-                                std::mem::swap(
-                                    &mut room0.students_female[ind0],
-                                    &mut room1.students_female[ind1],
-                                );
-                            });
+    let mut remove = None;
+    egui::Grid::new("teacher conflicts")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Teacher");
+            ui.strong("Student");
+            ui.strong("Reason");
+            ui.end_row();
+            for (i, conflict) in conflicts.iter_mut().enumerate() {
+                egui::ComboBox::from_id_salt(("conflict teacher", i))
+                    .selected_text(&conflict.teacher)
+                    .show_ui(ui, |ui| {
+                        for teacher in &teachers {
+                            ui.selectable_value(&mut conflict.teacher, teacher.clone(), teacher);
                         }
-                    }
-                    new.cal_overall_cost(&builder_new, average_score);
-
-                    builder_new.init();
-                    let Some(new) = builder_new.assign_result.take() else {
-                        return;
-                    };
-                    if new.overall_cost < best.overall_cost {
-                        best = new;
-                    }
-                    if k % n_step == 0 {
-                        let _ = tx
-                            .send_async(AppState::InProgress(k, n_loop, best.overall_cost.unwrap()))
-                            .await;
-                        ctx.request_repaint();
+                    });
+                ui.add(
+                    egui::DragValue::new(&mut conflict.student)
+                        .range(0..=n_students.saturating_sub(1) as u32),
+                );
+                ui_optional_text(ui, &mut conflict.reason);
+                if ui.button("🗑").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(i) = remove {
+        conflicts.remove(i);
+    }
+    if ui.button("Add conflict").clicked() {
+        conflicts.push(TeacherConflict {
+            teacher: teachers[0].clone(),
+            ..Default::default()
+        });
+    }
+}
 
-                        if cancel_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                            break;
-                        }
+fn ui_optional_text(ui: &mut egui::Ui, text: &mut Option<String>) {
+    ui.horizontal(|ui| {
+        let mut checked = text.is_some();
+        if ui.checkbox(&mut checked, "").changed() {
+            if checked {
+                *text = Some(String::new());
+            } else {
+                *text = None;
+            }
+        }
+        if let Some(text) = text.as_mut() {
+            ui.add(egui::TextEdit::singleline(text).desired_width(100.0));
+        }
+    });
+}
 
-                        #[cfg(target_arch = "wasm32")]
-                        {
-                            use gloo_timers::future::TimeoutFuture;
-                            TimeoutFuture::new(0).await;
-                        }
+fn ui_optional_capacity(ui: &mut egui::Ui, capacity: &mut Option<usize>) {
+    ui_optional_value(ui, capacity, 30, 0..=100);
+}
+
+fn ui_relationships(
+    ui: &mut egui::Ui,
+    relationships: &mut Vec<Relationship>,
+    n_students: usize,
+    reveal: bool,
+) {
+    let mut remove = None;
+    for (i, rel) in relationships.iter_mut().enumerate() {
+        if rel.confidential && !reveal {
+            ui.group(|ui| {
+                ui.label("🔒 confidential");
+            });
+            continue;
+        }
+        ui.push_id(("relationship", i), |ui| {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui_other_student(ui, &mut rel.from, rel.to, n_students);
+                    egui::ComboBox::from_id_salt("kind")
+                        .width(70.0)
+                        .selected_text(rel.kind.as_str())
+                        .show_ui(ui, |ui| {
+                            for kind in [RelationKind::Together, RelationKind::Apart] {
+                                ui.selectable_value(&mut rel.kind, kind, kind.as_str());
+                            }
+                        });
+                    ui_other_student(ui, &mut rel.to, rel.from, n_students);
+                    if ui.button("🗑").clicked() {
+                        remove = Some(i);
                     }
-                }
-                let _ = tx.send_async(AppState::Done(best)).await;
-                ctx.request_repaint();
-            }
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut rel.directed, "one-sided");
+                    ui.checkbox(&mut rel.hard, "hard");
+                    ui.checkbox(&mut rel.confidential, "🔒");
+                    ui.add(
+                        egui::DragValue::new(&mut rel.weight)
+                            .range(0.0..=100_000.0)
+                            .prefix("weight "),
+                    );
+                });
+            });
         });
     }
+    if let Some(i) = remove {
+        relationships.remove(i);
+    }
+    if n_students >= 2 && ui.button("Add relationship").clicked() {
+        relationships.push(Relationship::new(0, 1, RelationKind::Apart));
+    }
+}
+
+/// student id that is never `other`: a student is not related to themself
+fn ui_other_student(ui: &mut egui::Ui, iid: &mut StudentId, other: StudentId, n_students: usize) {
+    let before = *iid;
+    ui.add(egui::DragValue::new(iid).range(0..=n_students.saturating_sub(1) as StudentId));
+    if *iid == other {
+        // step over `other` in the direction of the change
+        let skipped = if *iid > before {
+            iid.checked_add(1)
+        } else {
+            iid.checked_sub(1)
+        };
+        *iid = skipped
+            .filter(|iid| (*iid as usize) < n_students)
+            .unwrap_or(before);
+    }
 }
 
 fn ui_like_group(ui: &mut egui::Ui, like_group: &mut [Vec<StudentId>]) {
@@ -466,18 +980,28 @@ fn ui_like_group(ui: &mut egui::Ui, like_group: &mut [Vec<StudentId>]) {
     }
 }
 
-fn ui_dislike_group(ui: &mut egui::Ui, dislike_group: &mut [Vec<StudentId>]) {
+fn ui_dislike_group(ui: &mut egui::Ui, dislike_group: &mut [SeparationGroup], reveal: bool) {
     for (igroup, group) in dislike_group.iter_mut().enumerate() {
-        ui.label(format!("Dislike Group {igroup}"));
+        ui.horizontal(|ui| {
+            ui.label(format!("Dislike Group {igroup}"));
+            if !group.confidential || reveal {
+                ui.checkbox(&mut group.confidential, "🔒");
+            }
+        });
         ui.group(|ui| {
-            for iid in group {
+            if group.confidential && !reveal {
+                ui.label(format!("confidential ({} students)", group.members.len()));
+                return;
+            }
+            for iid in &group.members {
                 ui.label(format!("Student {iid}"));
             }
         });
     }
 }
 
-fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student]) {
+fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student], n_class: u8) {
+    let n_students = students.len();
     ui.with_layout(Layout::right_to_left(egui::Align::Min), |ui| {
         let response = ui.button("import student list (csv)");
         response.on_hover_ui(|ui| {
@@ -499,6 +1023,9 @@ fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student]) {
                             //     .resizable(true),
         )
         .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
         .column(Column::remainder())
         // .column(Column::remainder())
         .min_scrolled_height(0.0)
@@ -518,6 +1045,18 @@ fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student]) {
             header.col(|ui| {
                 ui.strong("Score");
             });
+            header.col(|ui| {
+                ui.strong("Seats")
+                    .on_hover_text("seats taken in class size and capacity");
+            });
+            header.col(|ui| {
+                ui.strong("Class")
+                    .on_hover_text("pin to a class / exclude classes");
+            });
+            header.col(|ui| {
+                ui.strong("Friends")
+                    .on_hover_text("nominated friends, at least one should share the class");
+            });
             header.col(|ui| {
                 ui.strong("Note");
             });
@@ -552,6 +1091,15 @@ fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student]) {
                         // ui.checkbox(&mut self.checked, "Click me");
                         ui.label(format!("{:.1}", student.score));
                     });
+                    row.col(|ui| {
+                        ui.add(egui::DragValue::new(&mut student.seat_weight).range(1..=3));
+                    });
+                    row.col(|ui| {
+                        ui_student_placement(ui, student, n_class);
+                    });
+                    row.col(|ui| {
+                        ui_student_nominations(ui, student, n_students);
+                    });
                     row.col(|ui| {
                         // ui.label(long_text(row_index));
                         let mut checked = student.note.is_some();
@@ -571,6 +1119,86 @@ fn ui_student_table(ui: &mut egui::Ui, students: &mut [Student]) {
         });
 }
 
+fn ui_student_nominations(ui: &mut egui::Ui, student: &mut Student, n_students: usize) {
+    let text = student
+        .nominations
+        .iter()
+        .map(|iid| iid.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    ui.menu_button(format!("[{text}]"), |ui| {
+        // neither the student themself nor someone already named
+        let own = student.id;
+        let named = &student.nominations.clone();
+        let candidates =
+            || (0..n_students as StudentId).filter(move |iid| *iid != own && !named.contains(iid));
+        let mut remove = None;
+        for (i, friend) in student.nominations.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("friend", own, i))
+                    .width(50.0)
+                    .selected_text(friend.to_string())
+                    .show_ui(ui, |ui| {
+                        for iid in candidates() {
+                            ui.selectable_value(friend, iid, iid.to_string());
+                        }
+                    });
+                if ui.button("🗑").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            student.nominations.remove(i);
+        }
+        if student.nominations.len() < 3
+            && let Some(iid) = candidates().next()
+            && ui.button("Add friend").clicked()
+        {
+            student.nominations.push(iid);
+        }
+    });
+}
+
+fn ui_student_placement(ui: &mut egui::Ui, student: &mut Student, n_class: u8) {
+    if student.is_locked() {
+        ui.label("📌")
+            .on_hover_text("placement restricted by a pin or an exclusion");
+    } else {
+        ui.label("  ");
+    }
+
+    egui::ComboBox::from_id_salt(("pinned class", student.id))
+        .width(50.0)
+        .selected_text(match student.pinned_class {
+            Some(c) => format!("#{c}"),
+            None => "Any".to_owned(),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut student.pinned_class, None, "Any");
+            for c in 0..n_class {
+                ui.selectable_value(&mut student.pinned_class, Some(c), format!("#{c}"));
+            }
+        });
+
+    ui.menu_button(
+        format!("not in ({})", student.excluded_classes.len()),
+        |ui| {
+            for c in 0..n_class {
+                let mut excluded = student.excluded_classes.contains(&c);
+                if ui.checkbox(&mut excluded, format!("#{c}")).changed() {
+                    if excluded {
+                        student.excluded_classes.push(c);
+                        student.excluded_classes.sort_unstable();
+                    } else {
+                        student.excluded_classes.retain(|&k| k != c);
+                    }
+                }
+            }
+        },
+    );
+}
+
 impl eframe::App for MainApp {
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
@@ -0,0 +1,284 @@
+use rand::{Rng, rngs::StdRng};
+
+use super::{AssignResult, BuilderData, CostContext, Evaluator, Solver, is_better, sample_move};
+
+/// how the temperature goes from the start to the end temperature
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum CoolingSchedule {
+    /// multiplied by a constant factor each iteration
+    #[default]
+    Geometric,
+    /// decreased by a constant step each iteration
+    Linear,
+    /// raised or lowered to follow a target acceptance ratio of worsening moves
+    Adaptive,
+}
+
+impl CoolingSchedule {
+    pub(crate) const ALL: [Self; 3] = [Self::Geometric, Self::Linear, Self::Adaptive];
+
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Self::Geometric => "Geometric",
+            Self::Linear => "Linear",
+            Self::Adaptive => "Adaptive",
+        }
+    }
+}
+
+/// simulated annealing settings, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct AnnealingConfig {
+    /// in units of soft cost: a worsening by this much is accepted with p = 1/e at the start
+    pub(crate) start_temperature: f32,
+    pub(crate) end_temperature: f32,
+    pub(crate) schedule: CoolingSchedule,
+    /// adaptive schedule: acceptance ratio of worsening moves at the start, going down to 0
+    pub(crate) target_acceptance: f32,
+    /// reheat after this many iterations without a new best (0 = never)
+    pub(crate) reheat_after: usize,
+    /// reheat to this fraction of the start temperature
+    pub(crate) reheat_ratio: f32,
+}
+
+impl Default for AnnealingConfig {
+    fn default() -> Self {
+        Self {
+            start_temperature: 1000.0,
+            end_temperature: 1.0,
+            schedule: CoolingSchedule::default(),
+            target_acceptance: 0.3,
+            reheat_after: 5_000,
+            reheat_ratio: 0.5,
+        }
+    }
+}
+
+/// adaptive schedule: number of worsening proposals between two adjustments
+const ADAPT_WINDOW: usize = 100;
+/// adaptive schedule: temperature factor of one adjustment
+const ADAPT_FACTOR: f32 = 0.9;
+
+/// temperature of one annealing run: schedule, Metropolis acceptance and reheating
+#[derive(Debug, Clone)]
+pub(crate) struct Temperature {
+    config: AnnealingConfig,
+    n_iteration: usize,
+    temperature: f32,
+    /// iteration and temperature the schedule (re)started from
+    start_step: usize,
+    start_temperature: f32,
+    since_best: usize,
+    /// adaptive schedule: worsening moves proposed and accepted in the current window
+    worse_proposed: usize,
+    worse_accepted: usize,
+}
+
+impl Temperature {
+    pub(crate) fn new(config: &AnnealingConfig, n_iteration: usize) -> Self {
+        let start_temperature = config.start_temperature.max(f32::MIN_POSITIVE);
+        Self {
+            config: config.clone(),
+            n_iteration,
+            temperature: start_temperature,
+            start_step: 0,
+            start_temperature,
+            since_best: 0,
+            worse_proposed: 0,
+            worse_accepted: 0,
+        }
+    }
+
+    pub(crate) fn current(&self) -> f32 {
+        self.temperature
+    }
+
+    /// Metropolis rule on the (hard violations, soft cost) objective:
+    /// fewer hard violations is always accepted, more is never accepted,
+    /// otherwise a soft worsening `delta` is accepted with p = exp(-delta / T)
+    pub(crate) fn accepts(
+        &mut self,
+        current: (usize, f32),
+        candidate: (usize, f32),
+        rng: &mut impl Rng,
+    ) -> bool {
+        if candidate.0 != current.0 {
+            return candidate.0 < current.0;
+        }
+        let delta = candidate.1 - current.1;
+        if delta <= 0.0 {
+            return true;
+        }
+        let accepted = rng.random::<f32>() < libm::expf(-delta / self.temperature);
+        self.worse_proposed += 1;
+        self.worse_accepted += usize::from(accepted);
+        accepted
+    }
+
+    /// cool down after iteration `step`, or reheat if the best did not improve for too long
+    pub(crate) fn step(&mut self, step: usize, improved_best: bool) {
+        self.since_best = if improved_best {
+            0
+        } else {
+            self.since_best + 1
+        };
+        if self.config.reheat_after > 0 && self.since_best >= self.config.reheat_after {
+            self.since_best = 0;
+            self.start_step = step;
+            self.start_temperature =
+                (self.config.start_temperature * self.config.reheat_ratio).max(f32::MIN_POSITIVE);
+            self.temperature = self.start_temperature;
+            return;
+        }
+
+        let end = self.config.end_temperature.max(f32::MIN_POSITIVE);
+        let span = self.n_iteration.saturating_sub(self.start_step).max(1);
+        let progress = ((step + 1 - self.start_step) as f32 / span as f32).min(1.0);
+        self.temperature = match self.config.schedule {
+            CoolingSchedule::Geometric => {
+                self.start_temperature * libm::powf(end / self.start_temperature, progress)
+            }
+            CoolingSchedule::Linear => {
+                self.start_temperature + (end - self.start_temperature) * progress
+            }
+            CoolingSchedule::Adaptive => {
+                if self.worse_proposed >= ADAPT_WINDOW {
+                    let target = self.config.target_acceptance * (1.0 - progress);
+                    let ratio = self.worse_accepted as f32 / self.worse_proposed as f32;
+                    if ratio > target {
+                        self.temperature *= ADAPT_FACTOR;
+                    } else {
+                        self.temperature /= ADAPT_FACTOR;
+                    }
+                    self.worse_proposed = 0;
+                    self.worse_accepted = 0;
+                }
+                self.temperature.clamp(end, self.start_temperature.max(end))
+            }
+        }
+        .max(f32::MIN_POSITIVE);
+    }
+}
+
+/// one simulated annealing run: random moves (see [`sample_move`]),
+/// accepted by [`Temperature::accepts`] and undone otherwise
+pub(crate) struct Annealing<'a> {
+    bdata: &'a BuilderData,
+    evaluator: Evaluator<'a>,
+    temperature: Temperature,
+    iteration: usize,
+    current: (usize, f32),
+    best: AssignResult,
+    best_objective: (usize, f32),
+}
+
+impl<'a> Annealing<'a> {
+    pub(crate) fn new(
+        bdata: &'a BuilderData,
+        ctx: &'a CostContext,
+        config: &AnnealingConfig,
+        initial: AssignResult,
+        n_iteration: usize,
+    ) -> Self {
+        let evaluator = Evaluator::new(bdata, ctx, initial);
+        let current = evaluator.objective();
+        Self {
+            bdata,
+            best: evaluator.assignment().clone(),
+            best_objective: current,
+            current,
+            evaluator,
+            temperature: Temperature::new(config, n_iteration),
+            iteration: 0,
+        }
+    }
+
+    /// one iteration, returns true if the best improved
+    pub(crate) fn step(&mut self, rng: &mut impl Rng) -> bool {
+        let mut improved = false;
+        if let Some(mv) = sample_move(&self.evaluator, self.bdata, &self.bdata.moves, rng) {
+            let undo = self.evaluator.apply(mv);
+            let candidate = self.evaluator.objective();
+            if self.temperature.accepts(self.current, candidate, rng) {
+                self.current = candidate;
+                if is_better(self.current, self.best_objective) {
+                    self.best_objective = self.current;
+                    self.best = self.evaluator.assignment().clone();
+                    improved = true;
+                }
+            } else {
+                self.evaluator.revert(undo);
+            }
+        }
+        self.temperature.step(self.iteration, improved);
+        self.iteration += 1;
+        improved
+    }
+}
+
+impl Solver for Annealing<'_> {
+    fn step(&mut self, rng: &mut StdRng) -> Result<usize, String> {
+        Annealing::step(self, rng);
+        Ok(1)
+    }
+    fn best_objective(&self) -> (usize, f32) {
+        self.best_objective
+    }
+    fn temperature(&self) -> Option<f32> {
+        Some(self.temperature.current())
+    }
+    fn into_best(self: Box<Self>) -> AssignResult {
+        self.best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+
+    use super::*;
+
+    #[test]
+    fn annealing_cools_down_to_the_end_temperature() {
+        for schedule in [CoolingSchedule::Geometric, CoolingSchedule::Linear] {
+            let config = AnnealingConfig {
+                start_temperature: 100.0,
+                end_temperature: 1.0,
+                schedule,
+                reheat_after: 0,
+                ..AnnealingConfig::default()
+            };
+            let mut temperature = Temperature::new(&config, 10);
+            let mut last = temperature.current();
+            for k in 0..10 {
+                temperature.step(k, false);
+                assert!(temperature.current() < last);
+                last = temperature.current();
+            }
+            assert!((last - 1.0).abs() < 1e-3);
+        }
+
+        // no new best for 3 iterations: back to half the start temperature
+        let config = AnnealingConfig {
+            start_temperature: 100.0,
+            reheat_after: 3,
+            reheat_ratio: 0.5,
+            ..AnnealingConfig::default()
+        };
+        let mut temperature = Temperature::new(&config, 10);
+        (0..3).for_each(|k| temperature.step(k, false));
+        assert_eq!(temperature.current(), 50.0);
+    }
+
+    #[test]
+    fn metropolis_ranks_hard_violations_first() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut temperature = Temperature::new(&AnnealingConfig::default(), 10);
+        assert!(temperature.accepts((0, 10.0), (0, 5.0), &mut rng));
+        assert!(temperature.accepts((1, 10.0), (0, 1e9), &mut rng));
+        assert!(!temperature.accepts((0, 10.0), (1, 0.0), &mut rng));
+        // far worse than the temperature: never accepted
+        assert!(!temperature.accepts((0, 10.0), (0, 1e9), &mut rng));
+    }
+}
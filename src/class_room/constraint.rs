@@ -0,0 +1,379 @@
+use super::{AssignResult, BuilderData, ClassConfig, RelationKind, Student, StudentId};
+
+/// the homeroom teacher `teacher` must not have `student` in the class
+/// (own child, relative, documented conflict)
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct TeacherConflict {
+    pub(crate) teacher: String,
+    pub(crate) student: StudentId,
+    pub(crate) reason: Option<String>,
+}
+
+impl TeacherConflict {
+    /// true if the class is led by the conflicting teacher
+    pub(crate) fn blocks(&self, config: &ClassConfig) -> bool {
+        config
+            .teacher
+            .as_ref()
+            .is_some_and(|teacher| same_teacher(teacher, &self.teacher))
+    }
+}
+
+fn same_teacher(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim(), b.trim());
+    !a.is_empty() && a.eq_ignore_ascii_case(b)
+}
+
+/// reason why the hard placement constraints can not be satisfied
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Infeasible {
+    /// pins, exclusions and teacher conflicts leave no class for the student
+    NoAllowedClass { student: StudentId },
+    /// the student is pinned to a class led by a conflicting teacher
+    PinnedToConflict {
+        student: StudentId,
+        class_id: u8,
+        teacher: String,
+    },
+    /// students that can only go to this class need more seats than its maximum
+    OverCapacity {
+        class_id: u8,
+        seats: usize,
+        max: usize,
+    },
+    /// all classes together have fewer seats than the students need
+    NotEnoughSeats { seats: usize, capacity: usize },
+    /// no class is led by the teacher of a conflict: a renamed or mistyped teacher
+    /// would silently drop it
+    UnknownTeacher { student: StudentId, teacher: String },
+}
+
+impl std::fmt::Display for Infeasible {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAllowedClass { student } => write!(
+                f,
+                "student {student}: no class left after pins, exclusions and teacher conflicts"
+            ),
+            Self::PinnedToConflict {
+                student,
+                class_id,
+                teacher,
+            } => write!(
+                f,
+                "student {student}: pinned to class #{class_id}, but its teacher {teacher} has a conflict"
+            ),
+            Self::OverCapacity {
+                class_id,
+                seats,
+                max,
+            } => write!(
+                f,
+                "class #{class_id}: {seats} seats needed by students who can only go there, max. {max}"
+            ),
+            Self::NotEnoughSeats { seats, capacity } => write!(
+                f,
+                "{seats} seats needed, but all classes together allow only {capacity}"
+            ),
+            Self::UnknownTeacher { student, teacher } => write!(
+                f,
+                "student {student}: conflict with teacher {teacher}, who leads no class"
+            ),
+        }
+    }
+}
+
+impl BuilderData {
+    /// check the hard placement constraints before solving.
+    /// returns every problem found, not only the first one.
+    pub(crate) fn check_feasibility(&self) -> Result<(), Vec<Infeasible>> {
+        let mut problems = Vec::new();
+        let mut forced_seats = vec![0; self.n_class as usize];
+
+        for conflict in &self.teacher_conflicts {
+            if !(0..self.n_class).any(|c| conflict.blocks(self.class_config(c))) {
+                problems.push(Infeasible::UnknownTeacher {
+                    student: conflict.student,
+                    teacher: conflict.teacher.trim().to_owned(),
+                });
+            }
+        }
+
+        for st in &self.students {
+            if let Some(class_id) = st.pinned_class.filter(|&c| c < self.n_class)
+                && let Some(conflict) = self.teacher_conflicts.iter().find(|conflict| {
+                    conflict.student == st.id && conflict.blocks(self.class_config(class_id))
+                })
+            {
+                problems.push(Infeasible::PinnedToConflict {
+                    student: st.id,
+                    class_id,
+                    teacher: conflict.teacher.clone(),
+                });
+                continue;
+            }
+
+            let allowed = (0..self.n_class)
+                .filter(|&c| self.allows(st, c))
+                .collect::<Vec<_>>();
+            match allowed.as_slice() {
+                [] => problems.push(Infeasible::NoAllowedClass { student: st.id }),
+                [class_id] => forced_seats[*class_id as usize] += st.seat_weight as usize,
+                _ => {}
+            }
+        }
+
+        for (class_id, seats) in (0..self.n_class).zip(forced_seats) {
+            if let Some(max) = self.class_config(class_id).capacity_max
+                && seats > max
+            {
+                problems.push(Infeasible::OverCapacity {
+                    class_id,
+                    seats,
+                    max,
+                });
+            }
+        }
+
+        let capacity = (0..self.n_class)
+            .map(|c| self.class_config(c).capacity_max)
+            .sum::<Option<usize>>();
+        let seats = self
+            .students
+            .iter()
+            .map(|st| st.seat_weight as usize)
+            .sum::<usize>();
+        if let Some(capacity) = capacity
+            && seats > capacity
+        {
+            problems.push(Infeasible::NotEnoughSeats { seats, capacity });
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// one broken rule of an assignment and the students breaking it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Violation {
+    pub(crate) hard: bool,
+    pub(crate) class_id: u8,
+    pub(crate) rule: String,
+    /// empty for confidential rules
+    pub(crate) students: Vec<StudentId>,
+}
+
+impl AssignResult {
+    /// every broken hard and soft rule, hard ones first
+    pub(crate) fn violations(&self, bdata: &BuilderData) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for room in &self.rooms {
+            for iid in room.students() {
+                let st = &bdata.students[*iid as usize];
+                if let Some(rule) = bdata.placement_rule(st, room.number) {
+                    violations.push(Violation {
+                        hard: true,
+                        class_id: room.number,
+                        rule,
+                        students: vec![st.id],
+                    });
+                }
+            }
+
+            let seats = room.number_of_seats(&bdata.students);
+            if !bdata.seats_within_capacity(room.number, seats) {
+                let config = bdata.class_config(room.number);
+                let limit = |limit: Option<usize>| limit.map_or("-".to_owned(), |n| n.to_string());
+                violations.push(Violation {
+                    hard: true,
+                    class_id: room.number,
+                    rule: format!(
+                        "capacity: {seats} seats, allowed {}..{}",
+                        limit(config.capacity_min),
+                        limit(config.capacity_max)
+                    ),
+                    students: Vec::new(),
+                });
+            }
+        }
+
+        for rel in bdata.all_relationships() {
+            let Some(room) = self.rooms.iter().find(|c| c.contains(rel.from)) else {
+                continue;
+            };
+            if !rel.is_broken(room.contains(rel.to)) {
+                continue;
+            }
+            let (rule, students) = if rel.confidential {
+                ("confidential separation".to_owned(), Vec::new())
+            } else {
+                let rule = match rel.kind {
+                    RelationKind::Together => "should be together",
+                    RelationKind::Apart => "must be apart",
+                };
+                let rule = if rel.directed {
+                    format!("{rule} (one-sided)")
+                } else {
+                    rule.to_owned()
+                };
+                (rule, vec![rel.from, rel.to])
+            };
+            violations.push(Violation {
+                hard: rel.hard,
+                class_id: room.number,
+                rule,
+                students,
+            });
+        }
+
+        for room in &self.rooms {
+            let isolated = room
+                .students()
+                .filter(|iid| room.is_isolated(&bdata.students[**iid as usize]))
+                .copied()
+                .collect::<Vec<_>>();
+            if !isolated.is_empty() {
+                violations.push(Violation {
+                    hard: false,
+                    class_id: room.number,
+                    rule: "no nominated friend in the class".to_owned(),
+                    students: isolated,
+                });
+            }
+        }
+
+        violations.sort_by_key(|v| !v.hard);
+        violations
+    }
+}
+
+impl BuilderData {
+    /// why `st` must not be in class `class_id`, None if allowed
+    pub(crate) fn placement_rule(&self, st: &Student, class_id: u8) -> Option<String> {
+        if let Some(pinned) = st.pinned_class
+            && pinned != class_id
+        {
+            return Some(format!("pinned to class #{pinned}"));
+        }
+        if st.excluded_classes.contains(&class_id) {
+            return Some("excluded from this class".to_owned());
+        }
+        self.teacher_conflicts
+            .iter()
+            .find(|conflict| {
+                conflict.student == st.id && conflict.blocks(self.class_config(class_id))
+            })
+            .map(|conflict| format!("conflict with teacher {}", conflict.teacher.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Gender;
+    use super::super::fixture::builder;
+    use super::*;
+
+    fn conflict(teacher: &str, student: StudentId) -> TeacherConflict {
+        TeacherConflict {
+            teacher: teacher.to_owned(),
+            student,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn teacher_conflict_blocks_the_class_of_the_teacher() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        bdata.class_configs[1].teacher = Some(" Kim ".to_owned());
+        bdata.teacher_conflicts = vec![conflict("kim", 0)];
+        assert!(!bdata.allows(&bdata.students[0], 1));
+        assert!(bdata.allows(&bdata.students[0], 0));
+        assert!(bdata.allows(&bdata.students[1], 1));
+        assert!(bdata.check_feasibility().is_ok());
+    }
+
+    #[test]
+    fn student_without_allowed_class_is_infeasible() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        bdata.class_configs[1].teacher = Some("Kim".to_owned());
+        bdata.students[0].excluded_classes = vec![0];
+        bdata.teacher_conflicts = vec![conflict("Kim", 0)];
+        assert_eq!(
+            bdata.check_feasibility(),
+            Err(vec![Infeasible::NoAllowedClass { student: 0 }])
+        );
+    }
+
+    #[test]
+    fn pin_to_a_conflicting_teacher_is_infeasible() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        bdata.class_configs[1].teacher = Some("Kim".to_owned());
+        bdata.students[1].pinned_class = Some(1);
+        bdata.teacher_conflicts = vec![conflict("Kim", 1)];
+        assert_eq!(
+            bdata.check_feasibility(),
+            Err(vec![Infeasible::PinnedToConflict {
+                student: 1,
+                class_id: 1,
+                teacher: "Kim".to_owned(),
+            }])
+        );
+    }
+
+    #[test]
+    fn conflict_with_a_teacher_of_no_class_is_reported() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        bdata.class_configs[1].teacher = Some("Kim".to_owned());
+        bdata.teacher_conflicts = vec![conflict("Kim", 0), conflict(" Kym ", 1)];
+        assert_eq!(
+            bdata.check_feasibility(),
+            Err(vec![Infeasible::UnknownTeacher {
+                student: 1,
+                teacher: "Kym".to_owned(),
+            }])
+        );
+    }
+
+    #[test]
+    fn forced_students_over_the_maximum_are_infeasible() {
+        let mut bdata = builder(&[Gender::Male; 4]);
+        bdata.class_configs[0].capacity_max = Some(2);
+        bdata.students[0].pinned_class = Some(0);
+        bdata.students[1].excluded_classes = vec![1];
+        bdata.students[1].seat_weight = 2;
+        assert_eq!(
+            bdata.check_feasibility(),
+            Err(vec![Infeasible::OverCapacity {
+                class_id: 0,
+                seats: 3,
+                max: 2,
+            }])
+        );
+    }
+
+    #[test]
+    fn more_seats_than_all_classes_allow_is_infeasible() {
+        let mut bdata = builder(&[Gender::Male; 5]);
+        bdata.students[4].seat_weight = 2;
+        for config in &mut bdata.class_configs {
+            config.capacity_max = Some(3);
+        }
+        // 6 seats fit exactly
+        assert!(bdata.check_feasibility().is_ok());
+
+        bdata.students[4].seat_weight = 3;
+        assert_eq!(
+            bdata.check_feasibility(),
+            Err(vec![Infeasible::NotEnoughSeats {
+                seats: 7,
+                capacity: 6,
+            }])
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use rand::Rng;
+use rand::seq::SliceRandom as _;
+
+use super::{AssignResult, BuilderData, CostContext, Gender, RelationKind, Relationship};
+
+/// students that have to share a class: a cluster of hard "together" relationships,
+/// or a single student
+struct Unit {
+    members: Vec<usize>,
+    seats: usize,
+    /// classes every member is allowed in (pins, exclusions, teacher conflicts)
+    allowed: Vec<u8>,
+}
+
+/// greedy construction of a starting assignment, most constrained students first:
+/// pinned and restricted students, must-together clusters and the students with
+/// "apart" relationships, placed like a graph colouring (fewest conflict-free classes
+/// first). the others follow by descending score, each in the least filled class
+/// of their gender, which spreads scores and genders evenly.
+/// ties are broken at random, so repeated runs give different assignments.
+pub(crate) fn construct(
+    bdata: &BuilderData,
+    ctx: &CostContext,
+    rng: &mut impl Rng,
+) -> AssignResult {
+    let n_students = bdata.students.len();
+    let mut incident = vec![Vec::new(); n_students];
+    for rel in &ctx.relations {
+        let (from, to) = (rel.from as usize, rel.to as usize);
+        if from != to && from < n_students && to < n_students {
+            incident[from].push((to, rel));
+            incident[to].push((from, rel));
+        }
+    }
+
+    let mut state = Construction {
+        bdata,
+        ctx,
+        incident,
+        class_of: vec![None; n_students],
+        seats: vec![0; bdata.n_class as usize],
+        genders: vec![[0; 2]; bdata.n_class as usize],
+    };
+    let units = units(bdata, ctx);
+
+    let (mut constrained, mut free): (Vec<_>, Vec<_>) = units.iter().partition(|unit| {
+        unit.members.len() > 1
+            || unit.allowed.len() < bdata.n_class as usize
+            || unit.members.iter().any(|&s| {
+                state.incident[s]
+                    .iter()
+                    .any(|(_, rel)| rel.kind == RelationKind::Apart)
+            })
+    });
+
+    // colouring: the unit with the fewest classes left without a hard conflict,
+    // then the one with the most "apart" relationships
+    constrained.shuffle(rng);
+    while !constrained.is_empty() {
+        let next = (0..constrained.len())
+            .min_by_key(|&k| {
+                let unit = constrained[k];
+                let options = unit
+                    .allowed
+                    .iter()
+                    .filter(|&&c| state.hard_conflicts(unit, c) == 0)
+                    .count();
+                let degree = unit
+                    .members
+                    .iter()
+                    .map(|&s| state.incident[s].len())
+                    .sum::<usize>();
+                (options, std::cmp::Reverse(degree))
+            })
+            .unwrap_or(0);
+        let unit = constrained.swap_remove(next);
+        state.place(unit, rng);
+    }
+
+    // the rest by score, genders dealt separately
+    free.shuffle(rng);
+    free.sort_by(|a, b| {
+        let (a, b) = (&bdata.students[a.members[0]], &bdata.students[b.members[0]]);
+        (a.gender != Gender::Male)
+            .cmp(&(b.gender != Gender::Male))
+            .then(
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+    });
+    for unit in free {
+        state.place(unit, rng);
+    }
+
+    let classes = state
+        .class_of
+        .iter()
+        .map(|c| c.unwrap_or(0))
+        .collect::<Vec<_>>();
+    AssignResult::from_classes(bdata, &classes)
+}
+
+/// clusters of hard "together" relationships (union-find), by first member
+fn units(bdata: &BuilderData, ctx: &CostContext) -> Vec<Unit> {
+    let n_students = bdata.students.len();
+    let mut parent = (0..n_students).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut s: usize) -> usize {
+        while parent[s] != s {
+            parent[s] = parent[parent[s]];
+            s = parent[s];
+        }
+        s
+    }
+    for rel in ctx
+        .relations
+        .iter()
+        .filter(|rel| rel.hard && rel.kind == RelationKind::Together)
+    {
+        let (from, to) = (rel.from as usize, rel.to as usize);
+        if from < n_students && to < n_students {
+            let (a, b) = (root(&mut parent, from), root(&mut parent, to));
+            parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut units: Vec<Unit> = Vec::new();
+    let mut unit_of = vec![usize::MAX; n_students];
+    for s in 0..n_students {
+        let r = root(&mut parent, s);
+        if unit_of[r] == usize::MAX {
+            unit_of[r] = units.len();
+            units.push(Unit {
+                members: Vec::new(),
+                seats: 0,
+                allowed: Vec::new(),
+            });
+        }
+        let unit = &mut units[unit_of[r]];
+        unit.members.push(s);
+        unit.seats += bdata.students[s].seat_weight as usize;
+    }
+    for unit in &mut units {
+        unit.allowed = (0..bdata.n_class)
+            .filter(|&c| {
+                unit.members
+                    .iter()
+                    .all(|&s| bdata.allows(&bdata.students[s], c))
+            })
+            .collect();
+    }
+    units
+}
+
+struct Construction<'a> {
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    /// (other student, relationship) of each student, by id
+    incident: Vec<Vec<(usize, &'a Relationship)>>,
+    class_of: Vec<Option<u8>>,
+    seats: Vec<usize>,
+    /// placed (male, female) per class
+    genders: Vec<[usize; 2]>,
+}
+
+impl Construction<'_> {
+    /// hard relationships `unit` would break in class `c`
+    fn hard_conflicts(&self, unit: &Unit, c: u8) -> usize {
+        self.relations_in(unit, c)
+            .filter(|(rel, in_c)| rel.hard && (rel.kind == RelationKind::Apart) == *in_c)
+            .count()
+    }
+
+    /// (relationship, other student is in `c`) of each relationship of `unit`
+    /// to an already placed student
+    fn relations_in(&self, unit: &Unit, c: u8) -> impl Iterator<Item = (&Relationship, bool)> {
+        unit.members.iter().flat_map(move |&s| {
+            self.incident[s].iter().filter_map(move |(other, rel)| {
+                self.class_of[*other].map(|other_c| (*rel, other_c == c))
+            })
+        })
+    }
+
+    /// the class with the fewest hard conflicts, then within the capacity and below its
+    /// target, then the lowest soft relationship cost, then the fewest of the same gender
+    fn place(&mut self, unit: &Unit, rng: &mut impl Rng) {
+        let weights = &self.bdata.cost_weights;
+        let mut classes = if unit.allowed.is_empty() {
+            (0..self.bdata.n_class).collect()
+        } else {
+            unit.allowed.clone()
+        };
+        classes.shuffle(rng);
+
+        let key = |c: u8| {
+            let seats = self.seats[c as usize] + unit.seats;
+            let over_capacity = self
+                .bdata
+                .class_config(c)
+                .capacity_max
+                .is_some_and(|max| seats > max);
+            let over_target = seats > self.ctx.target_sizes[c as usize];
+            let soft = self
+                .relations_in(unit, c)
+                .filter(|(rel, _)| !rel.hard)
+                .map(|(rel, in_c)| match (rel.kind, in_c) {
+                    (RelationKind::Apart, true) => rel.broken_weight() * weights.apart,
+                    (RelationKind::Together, false) => rel.broken_weight() * weights.together,
+                    _ => 0.0,
+                })
+                .sum::<f32>();
+            let same_gender = unit
+                .members
+                .iter()
+                .map(|&s| self.genders[c as usize][gender_index(self.bdata, s)])
+                .sum::<usize>();
+            (
+                self.hard_conflicts(unit, c),
+                over_capacity,
+                over_target,
+                soft,
+                same_gender,
+                self.seats[c as usize],
+            )
+        };
+        let class_id = classes
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                key(a)
+                    .partial_cmp(&key(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+
+        for &s in &unit.members {
+            self.class_of[s] = Some(class_id);
+            self.seats[class_id as usize] += self.bdata.students[s].seat_weight as usize;
+            self.genders[class_id as usize][gender_index(self.bdata, s)] += 1;
+        }
+    }
+}
+
+fn gender_index(bdata: &BuilderData, s: usize) -> usize {
+    usize::from(bdata.students[s].gender != Gender::Male)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng as _, rngs::StdRng};
+
+    use super::super::fixture::grade;
+    use super::*;
+
+    #[test]
+    fn greedy_start_places_hard_constraints_first() {
+        let mut bdata = grade();
+        bdata.students[15].pinned_class = Some(1);
+        bdata.relationships.extend([
+            Relationship {
+                hard: true,
+                ..Relationship::new(15, 16, RelationKind::Together)
+            },
+            Relationship {
+                hard: true,
+                ..Relationship::new(16, 17, RelationKind::Together)
+            },
+            Relationship {
+                hard: true,
+                ..Relationship::new(17, 18, RelationKind::Apart)
+            },
+        ]);
+        let ctx = CostContext::new(&bdata);
+
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut assign = construct(&bdata, &ctx, &mut rng);
+            assign.cal_overall_cost(&bdata, &ctx);
+            assert_eq!(assign.hard_violations, Some(0));
+            let classes = assign.classes(bdata.students.len());
+            assert_eq!((classes[0], classes[1]), (0, 2));
+            assert_eq!(&classes[15..18], &[1, 1, 1]);
+            assert_ne!(classes[18], 1);
+            assert_ne!(classes[11], classes[12]);
+            // a dislike group of 3 in 3 classes: one each
+            let mut disliked = classes[8..11].to_vec();
+            disliked.sort_unstable();
+            assert_eq!(disliked, vec![0, 1, 2]);
+            let seats = assign
+                .rooms
+                .iter()
+                .map(|room| room.number_of_seats(&bdata.students))
+                .collect::<Vec<_>>();
+            assert_eq!(seats, ctx.target_sizes);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use super::{BuilderData, Gender, Relationship, RoomStats, Student};
+
+/// weight of each term of the objective, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct CostWeights {
+    /// squared difference between class mean score and grade mean score
+    pub(crate) score_mean: f32,
+    /// score variance inside each class
+    pub(crate) score_variance: f32,
+    /// largest class variance, times the number of classes
+    pub(crate) max_variance: f32,
+    /// multiplies the weight of each broken "together" relationship (like groups and pairs)
+    pub(crate) together: f32,
+    /// multiplies the weight of each broken "apart" relationship (dislike groups and pairs)
+    pub(crate) apart: f32,
+    /// squared difference between seats and target seats
+    pub(crate) size: f32,
+    /// per seat below the minimum or above the maximum capacity
+    pub(crate) capacity: f32,
+    /// per student without a nominated friend in the class
+    pub(crate) isolation: f32,
+    /// male ratio of each class vs. the grade ratio, beyond the tolerance (squared, in students)
+    pub(crate) gender: f32,
+    /// deviation of the male ratio that costs nothing, e.g. 0.05 = 5%
+    pub(crate) gender_tolerance: f32,
+    /// students per score tier vs. the grade distribution (squared)
+    pub(crate) score_distribution: f32,
+    /// number of score tiers (quantiles of the grade), e.g. 3 = top/middle/bottom thirds
+    pub(crate) score_tiers: u8,
+}
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        Self {
+            score_mean: 1.0,
+            score_variance: 1.0,
+            max_variance: 1.0,
+            together: 1.0,
+            apart: 1.0,
+            size: 100.0,
+            capacity: 1000.0,
+            isolation: 2000.0,
+            gender: 100.0,
+            gender_tolerance: 0.05,
+            score_distribution: 10.0,
+            score_tiers: 3,
+        }
+    }
+}
+
+/// grade-wide values the terms compare against, computed once per evaluation or solver run
+#[derive(Debug, Clone)]
+pub(crate) struct CostContext {
+    pub(crate) average_score: f32,
+    /// target seats per class, see [`BuilderData::target_sizes`]
+    pub(crate) target_sizes: Vec<usize>,
+    /// all relationships incl. the groups as cliques, see [`BuilderData::all_relationships`]
+    pub(crate) relations: Vec<Relationship>,
+    /// grade-wide ratio of male students
+    pub(crate) male_ratio: f32,
+    /// score tier of each student, by id
+    pub(crate) student_tiers: Vec<u8>,
+    /// share of the grade in each score tier
+    pub(crate) tier_shares: Vec<f32>,
+}
+
+impl CostContext {
+    pub(crate) fn new(bdata: &BuilderData) -> Self {
+        let (student_tiers, tier_shares) =
+            score_tiers(&bdata.students, bdata.cost_weights.score_tiers);
+        Self {
+            average_score: bdata.average_score(),
+            target_sizes: bdata.target_sizes(),
+            relations: bdata.all_relationships(),
+            male_ratio: bdata
+                .students
+                .iter()
+                .filter(|st| st.gender == Gender::Male)
+                .count() as f32
+                / bdata.students.len().max(1) as f32,
+            student_tiers,
+            tier_shares,
+        }
+    }
+}
+
+/// split the grade into `n_tiers` score quantiles: (tier of each student, share of each tier)
+fn score_tiers(students: &[Student], n_tiers: u8) -> (Vec<u8>, Vec<f32>) {
+    let n_tiers = n_tiers.max(1);
+    let mut scores = students.iter().map(|st| st.score).collect::<Vec<_>>();
+    scores.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let thresholds = (1..n_tiers as usize)
+        .filter_map(|k| scores.get(k * scores.len() / n_tiers as usize).copied())
+        .collect::<Vec<_>>();
+
+    let student_tiers = students
+        .iter()
+        .map(|st| thresholds.partition_point(|t| st.score >= *t) as u8)
+        .collect::<Vec<_>>();
+    let mut tier_shares = vec![0.0; n_tiers as usize];
+    for tier in &student_tiers {
+        tier_shares[*tier as usize] += 1.0 / students.len() as f32;
+    }
+    (student_tiers, tier_shares)
+}
+
+/// one named, weighted part of the objective.
+/// a term looks at the aggregates of one room and/or of all rooms together
+/// (see [`RoomStats`]); the result is weighted by [`CostTerm::weight`].
+pub(crate) trait CostTerm: Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn weight(&self, weights: &CostWeights) -> f32;
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32;
+
+    /// unweighted contribution of one room
+    fn room_cost(&self, _room: &RoomStats, _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        0.0
+    }
+    /// unweighted contribution that depends on all rooms together
+    fn global_cost(&self, _rooms: &[RoomStats], _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        0.0
+    }
+}
+
+/// the objective is the sum of these terms
+pub(crate) static COST_TERMS: &[&dyn CostTerm] = &[
+    &ScoreMean,
+    &ScoreVariance,
+    &MaxVariance,
+    &Together,
+    &Apart,
+    &ClassSize,
+    &Capacity,
+    &Isolation,
+    &GenderBalance,
+    &ScoreDistribution,
+];
+
+pub(crate) struct ScoreMean;
+impl CostTerm for ScoreMean {
+    fn name(&self) -> &'static str {
+        "Score mean"
+    }
+    fn description(&self) -> &'static str {
+        "class mean score vs. grade mean score (squared)"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.score_mean
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.score_mean
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, ctx: &CostContext) -> f32 {
+        room.mean()
+            .map_or(0.0, |mean| (mean - ctx.average_score).powi(2))
+    }
+}
+
+pub(crate) struct ScoreVariance;
+impl CostTerm for ScoreVariance {
+    fn name(&self) -> &'static str {
+        "Score variance"
+    }
+    fn description(&self) -> &'static str {
+        "score variance inside each class"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.score_variance
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.score_variance
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        room.variance().unwrap_or(0.0)
+    }
+}
+
+pub(crate) struct MaxVariance;
+impl CostTerm for MaxVariance {
+    fn name(&self) -> &'static str {
+        "Max. variance"
+    }
+    fn description(&self) -> &'static str {
+        "largest class variance, times the number of classes"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.max_variance
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.max_variance
+    }
+    fn global_cost(&self, rooms: &[RoomStats], _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        let var_max = rooms
+            .iter()
+            .filter_map(|c| c.variance())
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or(0.0);
+        var_max * rooms.len() as f32
+    }
+}
+
+pub(crate) struct Together;
+impl CostTerm for Together {
+    fn name(&self) -> &'static str {
+        "Like"
+    }
+    fn description(&self) -> &'static str {
+        "weights of the broken \"together\" relationships (like groups and pairs), \
+         once for each student who wants it"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.together
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.together
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        room.relations.together_cost
+    }
+}
+
+pub(crate) struct Apart;
+impl CostTerm for Apart {
+    fn name(&self) -> &'static str {
+        "Dislike"
+    }
+    fn description(&self) -> &'static str {
+        "weights of the broken \"apart\" relationships (dislike groups and pairs), \
+         once for each student who wants it"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.apart
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.apart
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        room.relations.apart_cost
+    }
+}
+
+pub(crate) struct ClassSize;
+impl CostTerm for ClassSize {
+    fn name(&self) -> &'static str {
+        "Class size"
+    }
+    fn description(&self) -> &'static str {
+        "seats vs. target seats (squared)"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.size
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.size
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, ctx: &CostContext) -> f32 {
+        let target = ctx.target_sizes.get(room.number as usize).copied();
+        target.map_or(0.0, |target| (room.seats as f32 - target as f32).powi(2))
+    }
+}
+
+pub(crate) struct Capacity;
+impl CostTerm for Capacity {
+    fn name(&self) -> &'static str {
+        "Capacity"
+    }
+    fn description(&self) -> &'static str {
+        "seats below the min. or above the max. capacity"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.capacity
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.capacity
+    }
+    fn room_cost(&self, room: &RoomStats, bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        bdata.capacity_excess(room.number, room.seats) as f32
+    }
+}
+
+pub(crate) struct Isolation;
+impl CostTerm for Isolation {
+    fn name(&self) -> &'static str {
+        "Isolated students"
+    }
+    fn description(&self) -> &'static str {
+        "students without any nominated friend in the class"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.isolation
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.isolation
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, _ctx: &CostContext) -> f32 {
+        room.isolated as f32
+    }
+}
+
+pub(crate) struct GenderBalance;
+impl CostTerm for GenderBalance {
+    fn name(&self) -> &'static str {
+        "Gender ratio"
+    }
+    fn description(&self) -> &'static str {
+        "male ratio of the class vs. the grade ratio, beyond the tolerance (squared, in students)"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.gender
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.gender
+    }
+    fn room_cost(&self, room: &RoomStats, bdata: &BuilderData, ctx: &CostContext) -> f32 {
+        let n = room.n_students;
+        if n == 0 {
+            return 0.0;
+        }
+        let ratio = room.n_male as f32 / n as f32;
+        let excess =
+            ((ratio - ctx.male_ratio).abs() - bdata.cost_weights.gender_tolerance).max(0.0);
+        (excess * n as f32).powi(2)
+    }
+}
+
+pub(crate) struct ScoreDistribution;
+impl CostTerm for ScoreDistribution {
+    fn name(&self) -> &'static str {
+        "Score tiers"
+    }
+    fn description(&self) -> &'static str {
+        "students per score tier vs. the grade distribution (squared, in students)"
+    }
+    fn weight(&self, weights: &CostWeights) -> f32 {
+        weights.score_distribution
+    }
+    fn weight_mut<'w>(&self, weights: &'w mut CostWeights) -> &'w mut f32 {
+        &mut weights.score_distribution
+    }
+    fn room_cost(&self, room: &RoomStats, _bdata: &BuilderData, ctx: &CostContext) -> f32 {
+        let n = room.n_students as f32;
+        room.tier_counts
+            .iter()
+            .zip(&ctx.tier_shares)
+            .map(|(count, share)| (*count as f32 - share * n).powi(2))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::AssignResult;
+    use super::super::fixture::{builder, grade, room, started};
+    use super::*;
+
+    #[test]
+    fn cost_terms_are_separate_units() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female]);
+        bdata.students[0].score = 40.0;
+        bdata.students[1].score = 60.0;
+        bdata.students[2].score = 80.0;
+        let ctx = CostContext::new(&bdata);
+
+        let stats = room(&bdata, &[0, 1]).update(&bdata, &ctx);
+
+        // grade mean 60, room mean 50
+        assert_eq!(ScoreMean.room_cost(&stats, &bdata, &ctx), 100.0);
+        assert_eq!(ScoreVariance.room_cost(&stats, &bdata, &ctx), 100.0);
+        // 3 seats over 2 classes: targets 2 and 1
+        assert_eq!(ctx.target_sizes, vec![2, 1]);
+        assert_eq!(ClassSize.room_cost(&stats, &bdata, &ctx), 0.0);
+    }
+
+    #[test]
+    fn overall_cost_is_the_sum_of_the_terms() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female, Gender::Male]);
+        bdata.students[3].score = 90.0;
+        bdata.like_group = vec![vec![0, 3]];
+        let ctx = CostContext::new(&bdata);
+
+        let mut assign = AssignResult::new(2);
+        assign.rooms[0] = room(&bdata, &[0, 1]);
+        assign.rooms[1] = room(&bdata, &[2, 3]);
+        assign.rooms[1].number = 1;
+        assign.cal_overall_cost(&bdata, &ctx);
+
+        assert_eq!(assign.cost_terms.len(), COST_TERMS.len());
+        let relationships = COST_TERMS
+            .iter()
+            .position(|term| term.name() == Together.name())
+            .unwrap();
+        // a mutual pair: broken for each of the two
+        assert_eq!(assign.cost_terms[relationships], 2000.0);
+        assert_eq!(
+            assign.overall_cost,
+            Some(assign.cost_terms.iter().sum::<f32>())
+        );
+    }
+
+    #[test]
+    fn gender_ratio_beyond_tolerance_costs() {
+        let bdata = builder(&[Gender::Male, Gender::Female, Gender::Male, Gender::Female]);
+        let ctx = CostContext::new(&bdata);
+        assert_eq!(ctx.male_ratio, 0.5);
+
+        let balanced = RoomStats::new(&room(&bdata, &[0, 1]), &bdata, &ctx);
+        assert_eq!(GenderBalance.room_cost(&balanced, &bdata, &ctx), 0.0);
+
+        // all male: ratio 1.0, 0.45 beyond the tolerance, times 2 students
+        let males = RoomStats::new(&room(&bdata, &[0, 2]), &bdata, &ctx);
+        let cost = GenderBalance.room_cost(&males, &bdata, &ctx);
+        assert!((cost - 0.9_f32.powi(2)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn score_tiers_match_the_grade_distribution() {
+        let mut bdata = builder(&[Gender::Male; 6]);
+        for (st, score) in bdata
+            .students
+            .iter_mut()
+            .zip([10.0, 20.0, 50.0, 60.0, 80.0, 90.0])
+        {
+            st.score = score;
+        }
+        let ctx = CostContext::new(&bdata);
+        assert_eq!(ctx.student_tiers, vec![0, 0, 1, 1, 2, 2]);
+
+        // same mean as the grade, but only bottom and top
+        let extremes = RoomStats::new(&room(&bdata, &[0, 5, 1, 4]), &bdata, &ctx);
+        let spread = RoomStats::new(&room(&bdata, &[0, 2, 4]), &bdata, &ctx);
+        let term = ScoreDistribution;
+        assert!(term.room_cost(&extremes, &bdata, &ctx) > 0.0);
+        assert!(term.room_cost(&spread, &bdata, &ctx) < 1e-4);
+    }
+
+    #[test]
+    fn weight_scales_its_term_until_reset() {
+        let mut bdata = grade();
+        let mut classes = started(&mut bdata).classes(bdata.students.len());
+        // split the like group {3, 4, 5}
+        classes[4] = (classes[3] + 1) % 3;
+        let mut assign = AssignResult::from_classes(&bdata, &classes);
+        let ctx = CostContext::new(&bdata);
+        assign.cal_overall_cost(&bdata, &ctx);
+        let defaults = assign.cost_terms.clone();
+
+        let together = COST_TERMS
+            .iter()
+            .position(|term| term.name() == Together.name())
+            .unwrap();
+        assert!(defaults[together] > 0.0);
+        *COST_TERMS[together].weight_mut(&mut bdata.cost_weights) *= 3.0;
+        assign.cal_overall_cost(&bdata, &ctx);
+        for (k, (cost, default)) in assign.cost_terms.iter().zip(&defaults).enumerate() {
+            let expected = if k == together {
+                3.0 * default
+            } else {
+                *default
+            };
+            assert!((cost - expected).abs() < 1e-3, "{}", COST_TERMS[k].name());
+        }
+
+        // "reset to defaults" in the weights panel
+        bdata.cost_weights = CostWeights::default();
+        assign.cal_overall_cost(&bdata, &ctx);
+        assert_eq!(assign.cost_terms, defaults);
+    }
+}
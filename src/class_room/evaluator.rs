@@ -0,0 +1,455 @@
+use super::{
+    AssignResult, BrokenRelations, BuilderData, COST_TERMS, ClassRoom, CostContext, Gender,
+    StudentId,
+};
+
+/// class of a student that is in no room
+const UNASSIGNED: u8 = u8::MAX;
+
+/// running aggregates of one room: enough to evaluate every cost term without visiting the members
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RoomStats {
+    pub(crate) number: u8,
+    pub(crate) n_students: usize,
+    pub(crate) n_male: usize,
+    pub(crate) seats: usize,
+    score_sum: f64,
+    score_sq_sum: f64,
+    /// members per score tier, see [`CostContext::student_tiers`]
+    pub(crate) tier_counts: Vec<usize>,
+    pub(crate) relations: BrokenRelations,
+    pub(crate) isolated: usize,
+    /// members not allowed in this class (pins, exclusions, teacher conflicts)
+    pub(crate) misplaced: usize,
+}
+
+impl RoomStats {
+    /// aggregates of `room` from scratch
+    pub(crate) fn new(room: &ClassRoom, bdata: &BuilderData, ctx: &CostContext) -> Self {
+        let mut stats = Self {
+            number: room.number,
+            n_students: 0,
+            n_male: 0,
+            seats: 0,
+            score_sum: 0.0,
+            score_sq_sum: 0.0,
+            tier_counts: vec![0; ctx.tier_shares.len()],
+            relations: room.count_broken_relations(&ctx.relations),
+            isolated: room.count_isolated(&bdata.students),
+            misplaced: 0,
+        };
+        for iid in room.students() {
+            stats.add_member(*iid, bdata, ctx);
+        }
+        stats
+    }
+
+    /// member-only aggregates: counts, seats, score sums, tiers and placement
+    fn add_member(&mut self, sid: StudentId, bdata: &BuilderData, ctx: &CostContext) {
+        let st = &bdata.students[sid as usize];
+        let score = st.score as f64;
+        self.n_students += 1;
+        self.n_male += usize::from(st.gender == Gender::Male);
+        self.seats += st.seat_weight as usize;
+        self.score_sum += score;
+        self.score_sq_sum += score * score;
+        if let Some(tier) = ctx.student_tiers.get(sid as usize) {
+            self.tier_counts[*tier as usize] += 1;
+        }
+        self.misplaced += usize::from(!bdata.allows(st, self.number));
+    }
+    fn remove_member(&mut self, sid: StudentId, bdata: &BuilderData, ctx: &CostContext) {
+        let st = &bdata.students[sid as usize];
+        let score = st.score as f64;
+        self.n_students -= 1;
+        self.n_male -= usize::from(st.gender == Gender::Male);
+        self.seats -= st.seat_weight as usize;
+        self.score_sum -= score;
+        self.score_sq_sum -= score * score;
+        if let Some(tier) = ctx.student_tiers.get(sid as usize) {
+            self.tier_counts[*tier as usize] -= 1;
+        }
+        self.misplaced -= usize::from(!bdata.allows(st, self.number));
+    }
+
+    pub(crate) fn mean(&self) -> Option<f32> {
+        (self.n_students > 0).then(|| (self.score_sum / self.n_students as f64) as f32)
+    }
+    pub(crate) fn variance(&self) -> Option<f32> {
+        (self.n_students > 0).then(|| {
+            let n = self.n_students as f64;
+            let mean = self.score_sum / n;
+            (self.score_sq_sum / n - mean * mean).max(0.0) as f32
+        })
+    }
+
+    /// broken hard constraints: hard relationships, misplaced students, capacity
+    pub(crate) fn hard_violations(&self, bdata: &BuilderData) -> usize {
+        self.relations.hard
+            + self.misplaced
+            + usize::from(!bdata.seats_within_capacity(self.number, self.seats))
+    }
+
+    /// weighted cost of each room term of [`COST_TERMS`]
+    pub(crate) fn term_costs<'s>(
+        &'s self,
+        bdata: &'s BuilderData,
+        ctx: &'s CostContext,
+    ) -> impl Iterator<Item = f32> + 's {
+        COST_TERMS
+            .iter()
+            .map(move |term| term.room_cost(self, bdata, ctx) * term.weight(&bdata.cost_weights))
+    }
+}
+
+/// one local-search step, see [`sample_move`](super::sample_move)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Move {
+    /// student to class
+    Student(StudentId, u8),
+    /// exchange the classes of two students
+    Swap(StudentId, StudentId),
+    /// 3-cycle: the first to the class of the second, the second to the class of the third,
+    /// the third to the class of the first
+    Rotate(StudentId, StudentId, StudentId),
+    /// every member of a like group (index into [`BuilderData::like_group`]) to class
+    Group(usize, u8),
+}
+
+impl Move {
+    /// the students that may change class
+    pub(crate) fn students(&self, bdata: &BuilderData) -> Vec<StudentId> {
+        match *self {
+            Self::Student(sid, _) => vec![sid],
+            Self::Swap(sid0, sid1) => vec![sid0, sid1],
+            Self::Rotate(sid0, sid1, sid2) => vec![sid0, sid1, sid2],
+            Self::Group(group, _) => bdata
+                .like_group
+                .get(group)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|sid| (*sid as usize) < bdata.students.len())
+                .collect(),
+        }
+    }
+}
+
+/// assignment with cached per-room aggregates, for local search.
+/// moving a student only updates the two rooms involved, in O(degree) of the student
+/// (relationships and nominations); [`Self::objective`] then sums the cached room costs.
+/// to evaluate a proposal: apply it, read the objective, and undo it if rejected.
+pub(crate) struct Evaluator<'a> {
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    assign: AssignResult,
+    /// class of each student, by id
+    class_of: Vec<u8>,
+    /// nominated friends in the same class, by id
+    friends_here: Vec<usize>,
+    /// students who nominated each student, by id
+    nominated_by: Vec<Vec<StudentId>>,
+    /// indices into [`CostContext::relations`] of the relationships touching each student
+    incident: Vec<Vec<usize>>,
+    stats: Vec<RoomStats>,
+    /// weighted soft cost of each room
+    room_costs: Vec<f32>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub(crate) fn new(
+        bdata: &'a BuilderData,
+        ctx: &'a CostContext,
+        mut assign: AssignResult,
+    ) -> Self {
+        // not the exact solver's optimum anymore once moved
+        assign.optimality_gap = None;
+        let n_students = bdata.students.len();
+        let mut class_of = vec![UNASSIGNED; n_students];
+        for room in &assign.rooms {
+            for iid in room.students() {
+                class_of[*iid as usize] = room.number;
+            }
+        }
+
+        let mut nominated_by = vec![Vec::new(); n_students];
+        for st in &bdata.students {
+            for friend in &st.nominations {
+                if let Some(by) = nominated_by.get_mut(*friend as usize) {
+                    by.push(st.id);
+                }
+            }
+        }
+        let mut incident = vec![Vec::new(); n_students];
+        for (r, rel) in ctx.relations.iter().enumerate() {
+            if let Some(touching) = incident.get_mut(rel.from as usize) {
+                touching.push(r);
+            }
+            if rel.to != rel.from
+                && let Some(touching) = incident.get_mut(rel.to as usize)
+            {
+                touching.push(r);
+            }
+        }
+
+        let stats = assign
+            .rooms
+            .iter()
+            .map(|room| RoomStats::new(room, bdata, ctx))
+            .collect::<Vec<_>>();
+        let room_costs = stats
+            .iter()
+            .map(|stats| stats.term_costs(bdata, ctx).sum())
+            .collect();
+
+        let mut evaluator = Self {
+            bdata,
+            ctx,
+            assign,
+            class_of,
+            friends_here: vec![0; n_students],
+            nominated_by,
+            incident,
+            stats,
+            room_costs,
+        };
+        for sid in 0..n_students as StudentId {
+            evaluator.friends_here[sid as usize] = evaluator.count_friends_here(sid);
+        }
+        evaluator
+    }
+
+    /// the current assignment, not evaluated: see [`AssignResult::cal_overall_cost`]
+    pub(crate) fn assignment(&self) -> &AssignResult {
+        &self.assign
+    }
+    pub(crate) fn rooms(&self) -> &[ClassRoom] {
+        &self.assign.rooms
+    }
+    pub(crate) fn class_of(&self, sid: StudentId) -> u8 {
+        self.class_of[sid as usize]
+    }
+    /// cached weighted size of class `class_id`, see [`ClassRoom::number_of_seats`]
+    pub(crate) fn seats(&self, class_id: u8) -> usize {
+        self.stats[class_id as usize].seats
+    }
+
+    /// (hard violations, soft cost), same as [`AssignResult::objective`] after a full evaluation
+    pub(crate) fn objective(&self) -> (usize, f32) {
+        let hard = self
+            .stats
+            .iter()
+            .map(|stats| stats.hard_violations(self.bdata))
+            .sum();
+        let global = COST_TERMS
+            .iter()
+            .map(|term| {
+                term.global_cost(&self.stats, self.bdata, self.ctx)
+                    * term.weight(&self.bdata.cost_weights)
+            })
+            .sum::<f32>();
+        (hard, self.room_costs.iter().sum::<f32>() + global)
+    }
+
+    /// the current assignment, fully evaluated
+    #[cfg(test)]
+    pub(crate) fn result(&self) -> AssignResult {
+        let mut assign = self.assign.clone();
+        assign.cal_overall_cost(self.bdata, self.ctx);
+        assign
+    }
+
+    /// apply `mv`, returns the previous class of each student it moved: see [`Self::revert`]
+    pub(crate) fn apply(&mut self, mv: Move) -> Vec<(StudentId, u8)> {
+        let targets = match mv {
+            Move::Student(sid, to) => vec![(sid, to)],
+            Move::Swap(sid0, sid1) => {
+                vec![(sid0, self.class_of(sid1)), (sid1, self.class_of(sid0))]
+            }
+            Move::Rotate(sid0, sid1, sid2) => vec![
+                (sid0, self.class_of(sid1)),
+                (sid1, self.class_of(sid2)),
+                (sid2, self.class_of(sid0)),
+            ],
+            Move::Group(_, to) => mv
+                .students(self.bdata)
+                .into_iter()
+                .map(|sid| (sid, to))
+                .collect(),
+        };
+        let undo = targets
+            .iter()
+            .map(|(sid, _)| (*sid, self.class_of(*sid)))
+            .collect();
+        for (sid, to) in targets {
+            self.move_student(sid, to);
+        }
+        undo
+    }
+
+    /// undo [`Self::apply`]
+    pub(crate) fn revert(&mut self, undo: Vec<(StudentId, u8)>) {
+        for (sid, class_id) in undo.into_iter().rev() {
+            self.move_student(sid, class_id);
+        }
+    }
+
+    /// move student `sid` to class `to`, updating the two rooms involved
+    pub(crate) fn move_student(&mut self, sid: StudentId, to: u8) {
+        let from = self.class_of(sid);
+        if from == to || from == UNASSIGNED {
+            return;
+        }
+        let (bdata, ctx) = (self.bdata, self.ctx);
+        let gender = bdata.students[sid as usize].gender;
+        let from_list = gender_list(&mut self.assign.rooms[from as usize], gender);
+        if let Some(idx) = from_list.iter().position(|iid| *iid == sid) {
+            from_list.remove(idx);
+        }
+        gender_list(&mut self.assign.rooms[to as usize], gender).push(sid);
+
+        for r in 0..self.incident[sid as usize].len() {
+            self.count_relation(self.incident[sid as usize][r], false);
+        }
+        self.leave(sid, from);
+        self.stats[from as usize].remove_member(sid, bdata, ctx);
+
+        self.class_of[sid as usize] = to;
+
+        self.stats[to as usize].add_member(sid, bdata, ctx);
+        self.join(sid, to);
+        for r in 0..self.incident[sid as usize].len() {
+            self.count_relation(self.incident[sid as usize][r], true);
+        }
+
+        for class_id in [from, to] {
+            let stats = &self.stats[class_id as usize];
+            self.room_costs[class_id as usize] = stats.term_costs(bdata, ctx).sum();
+        }
+    }
+
+    /// isolation bookkeeping of `sid` leaving class `from`
+    fn leave(&mut self, sid: StudentId, from: u8) {
+        for k in 0..self.nominated_by[sid as usize].len() {
+            let by = self.nominated_by[sid as usize][k];
+            if by != sid && self.class_of(by) == from {
+                self.friends_here[by as usize] -= 1;
+                if self.friends_here[by as usize] == 0 {
+                    self.stats[from as usize].isolated += 1;
+                }
+            }
+        }
+        if self.is_isolated(sid) {
+            self.stats[from as usize].isolated -= 1;
+        }
+    }
+    /// isolation bookkeeping of `sid` joining class `to`
+    fn join(&mut self, sid: StudentId, to: u8) {
+        self.friends_here[sid as usize] = self.count_friends_here(sid);
+        if self.is_isolated(sid) {
+            self.stats[to as usize].isolated += 1;
+        }
+        for k in 0..self.nominated_by[sid as usize].len() {
+            let by = self.nominated_by[sid as usize][k];
+            if by != sid && self.class_of(by) == to {
+                if self.friends_here[by as usize] == 0 {
+                    self.stats[to as usize].isolated -= 1;
+                }
+                self.friends_here[by as usize] += 1;
+            }
+        }
+    }
+    fn count_friends_here(&self, sid: StudentId) -> usize {
+        let class_id = self.class_of(sid);
+        self.bdata.students[sid as usize]
+            .nominations
+            .iter()
+            .filter(|friend| self.class_of.get(**friend as usize) == Some(&class_id))
+            .count()
+    }
+    fn is_isolated(&self, sid: StudentId) -> bool {
+        !self.bdata.students[sid as usize].nominations.is_empty()
+            && self.friends_here[sid as usize] == 0
+    }
+
+    /// add (or remove) each broken side of relationship `r` to the room of its student
+    fn count_relation(&mut self, r: usize, add: bool) {
+        let rel = &self.ctx.relations[r];
+        for (side, (own, other)) in rel.sides().enumerate() {
+            let Some(&class_id) = self.class_of.get(own as usize) else {
+                continue;
+            };
+            let same_class = self.class_of.get(other as usize) == Some(&class_id);
+            if class_id == UNASSIGNED || !rel.is_broken(same_class) {
+                continue;
+            }
+            self.stats[class_id as usize]
+                .relations
+                .record(rel, side == 0, add);
+        }
+    }
+}
+
+fn gender_list(room: &mut ClassRoom, gender: Gender) -> &mut Vec<StudentId> {
+    match gender {
+        Gender::Male => &mut room.students_male,
+        Gender::Female => &mut room.students_female,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng as _, SeedableRng as _, rngs::StdRng};
+
+    use super::super::fixture::{grade, started};
+    use super::*;
+
+    #[test]
+    fn incremental_cost_matches_full_evaluation() {
+        let mut bdata = grade();
+        let assign = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut evaluator = Evaluator::new(&bdata, &ctx, assign);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let n_students = bdata.students.len() as StudentId;
+        for k in 0..300 {
+            let sid0 = rng.random_range(0..n_students);
+            match k % 3 {
+                0 => evaluator.move_student(sid0, rng.random_range(0..bdata.n_class)),
+                1 => {
+                    evaluator.apply(Move::Swap(sid0, rng.random_range(0..n_students)));
+                }
+                _ => {
+                    evaluator.apply(Move::Group(k % 2, rng.random_range(0..bdata.n_class)));
+                }
+            }
+        }
+
+        let (hard, soft) = evaluator.objective();
+        let (full_hard, full_soft) = evaluator.result().objective();
+        assert_eq!(hard, full_hard);
+        assert!((soft - full_soft).abs() <= full_soft.abs() * 1e-4);
+    }
+
+    #[test]
+    fn reverted_moves_restore_the_cost() {
+        let mut bdata = grade();
+        let assign = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut evaluator = Evaluator::new(&bdata, &ctx, assign);
+        let before = evaluator.objective();
+
+        for mv in [
+            Move::Student(3, 1),
+            Move::Swap(4, 9),
+            Move::Rotate(5, 8, 15),
+            Move::Group(0, 2),
+        ] {
+            let undo = evaluator.apply(mv);
+            evaluator.revert(undo);
+            assert_eq!(evaluator.objective().0, before.0, "{mv:?}");
+            assert!((evaluator.objective().1 - before.1).abs() < 1e-3, "{mv:?}");
+        }
+    }
+}
@@ -0,0 +1,742 @@
+use microlp::{
+    ComparisonOp, Error, LinearExpr, OptimizationDirection, Problem, Solution, Variable,
+};
+
+use rand::rngs::StdRng;
+
+use super::{AssignResult, BuilderData, CostContext, Gender, RelationKind, Solver};
+
+/// exact solver settings, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct ExactConfig {
+    /// above this many student × class variables, a heuristic runs instead.
+    /// the search time grows exponentially, and faster with more classes: 40 students in
+    /// 2 or 3 classes take well under a second, 30 in 4 classes about 15 s, 24 in 5 about 30 s.
+    /// a run stopped earlier by a limit keeps its gap, see [`AssignResult::optimality_gap`]
+    pub(crate) max_variables: usize,
+    /// relative gap between the solution and the lower bound at which the search stops:
+    /// the solution is then optimal up to this gap
+    pub(crate) gap_tolerance: f32,
+}
+
+impl Default for ExactConfig {
+    fn default() -> Self {
+        Self {
+            max_variables: 120,
+            gap_tolerance: 1e-4,
+        }
+    }
+}
+
+impl ExactConfig {
+    pub(crate) fn is_small_enough(&self, bdata: &BuilderData) -> bool {
+        bdata.students.len() * bdata.n_class as usize <= self.max_variables
+    }
+}
+
+/// `(variable, coefficient)` terms of a linear expression
+type Terms = Vec<(Variable, f64)>;
+
+/// integer program of the assignment: x[s][c] = 1 if student s is in class c.
+/// pins, exclusions, teacher conflicts, capacities and hard relationships are constraints.
+/// the squared soft terms (class size, score mean, gender ratio, score tiers) become piecewise
+/// linear, see [`Square`], broken relationships and isolated students get one indicator each,
+/// and the score variances are left out.
+/// every variable is continuous: [`Exact`] branches on the class aggregates and on x.
+struct Model {
+    problem: Problem,
+    /// x[s][c], `None` where the student is not allowed or left out by symmetry
+    x: Vec<Vec<Option<Variable>>>,
+    /// integer class aggregates: seats, students, males and students per score tier
+    aggregates: Vec<Terms>,
+    /// groups of interchangeable classes, see [`symmetric_classes`]
+    symmetric: Vec<Vec<u8>>,
+}
+
+impl Model {
+    fn new(bdata: &BuilderData, ctx: &CostContext) -> Self {
+        let weights = &bdata.cost_weights;
+        let n_class = bdata.n_class as usize;
+        let mut problem = Problem::new(OptimizationDirection::Minimize);
+        let indicator = |problem: &mut Problem, cost: f32| problem.add_var(cost as f64, (0.0, 1.0));
+
+        // the k-th student allowed in a group of interchangeable classes only goes
+        // to one of its first k + 1 classes: any assignment can be relabelled this way
+        let symmetric = symmetric_classes(bdata, ctx);
+        let mut first_classes = vec![usize::MAX; n_class];
+        for group in &symmetric {
+            for (k, c) in group.iter().enumerate() {
+                first_classes[*c as usize] = k;
+            }
+        }
+        let mut allowed_before = vec![0; n_class];
+        let x = bdata
+            .students
+            .iter()
+            .map(|st| {
+                let xs = (0..bdata.n_class)
+                    .map(|c| {
+                        let k = first_classes[c as usize];
+                        let group_rank = symmetric
+                            .iter()
+                            .position(|group| group.contains(&c))
+                            .map_or(0, |g| allowed_before[g]);
+                        (bdata.allows(st, c) && (k == usize::MAX || k <= group_rank))
+                            .then(|| indicator(&mut problem, 0.0))
+                    })
+                    .collect::<Vec<_>>();
+                for (g, group) in symmetric.iter().enumerate() {
+                    if bdata.allows(st, group[0]) {
+                        allowed_before[g] += 1;
+                    }
+                }
+                xs
+            })
+            .collect::<Vec<_>>();
+        let in_class = |c: usize, coeff: &dyn Fn(usize) -> f64| -> Terms {
+            x.iter()
+                .enumerate()
+                .filter_map(|(s, xs)| xs[c].map(|v| (v, coeff(s))))
+                .filter(|(_, coeff)| *coeff != 0.0)
+                .collect()
+        };
+
+        // each student in exactly one class
+        for xs in &x {
+            let expr = xs
+                .iter()
+                .flatten()
+                .map(|v| (*v, 1.0))
+                .collect::<LinearExpr>();
+            problem.add_constraint(expr, ComparisonOp::Eq, 1.0);
+        }
+
+        let seat = |s: usize| bdata.students[s].seat_weight as f64;
+        let is_male = |s: usize| f64::from(u8::from(bdata.students[s].gender == Gender::Male));
+        let in_tier = |s: usize, tier: usize| {
+            f64::from(u8::from(ctx.student_tiers.get(s) == Some(&(tier as u8))))
+        };
+        let mut aggregates = Vec::new();
+        for c in 0..n_class {
+            let config = bdata.class_config(c as u8);
+            if let Some(min) = config.capacity_min {
+                problem.add_constraint(in_class(c, &seat), ComparisonOp::Ge, min as f64);
+            }
+            if let Some(max) = config.capacity_max {
+                problem.add_constraint(in_class(c, &seat), ComparisonOp::Le, max as f64);
+            }
+            let target = ctx.target_sizes.get(c).copied().unwrap_or(0) as f64;
+            let size = Square::secants(&SIZE_POINTS);
+            size.add(&mut problem, &in_class(c, &seat), -target, weights.size);
+            // the mean against the target size, which keeps it linear
+            let score =
+                |s: usize| (bdata.students[s].score - ctx.average_score) as f64 / target.max(1.0);
+            let mean = Square::tangents(&SCORE_POINTS);
+            mean.add(&mut problem, &in_class(c, &score), 0.0, weights.score_mean);
+
+            // males beyond the grade ratio and its tolerance, in students:
+            // excess >= +-(males - ratio * students) - tolerance * students
+            let excess = problem.add_var(0.0, (0.0, f64::INFINITY));
+            let ratio = ctx.male_ratio as f64;
+            let tolerance = weights.gender_tolerance as f64;
+            for sign in [1.0, -1.0] {
+                let mut expr = in_class(c, &|s| sign * (is_male(s) - ratio) - tolerance)
+                    .into_iter()
+                    .collect::<LinearExpr>();
+                expr.add(excess, -1.0);
+                problem.add_constraint(expr, ComparisonOp::Le, 0.0);
+            }
+            let students = Square::tangents(&STUDENT_POINTS);
+            students.add(&mut problem, &[(excess, 1.0)], 0.0, weights.gender);
+            for (tier, share) in ctx.tier_shares.iter().enumerate() {
+                let off_share = |s: usize| in_tier(s, tier) - *share as f64;
+                let terms = in_class(c, &off_share);
+                students.add(&mut problem, &terms, 0.0, weights.score_distribution);
+            }
+
+            aggregates.push(in_class(c, &seat));
+            aggregates.push(in_class(c, &|_| 1.0));
+            aggregates.push(in_class(c, &is_male));
+            for tier in 0..ctx.tier_shares.len() {
+                aggregates.push(in_class(c, &|s| in_tier(s, tier)));
+            }
+        }
+
+        // broken indicator of each soft "apart" pair, for the clique cuts
+        let mut apart = std::collections::HashMap::new();
+        for rel in &ctx.relations {
+            let (Some(xa), Some(xb)) = (x.get(rel.from as usize), x.get(rel.to as usize)) else {
+                continue;
+            };
+            let cost = match rel.kind {
+                RelationKind::Together => rel.broken_weight() * weights.together,
+                RelationKind::Apart => rel.broken_weight() * weights.apart,
+            };
+            let broken = (!rel.hard).then(|| indicator(&mut problem, cost));
+            if rel.kind == RelationKind::Apart {
+                let pair = (rel.from.min(rel.to), rel.from.max(rel.to));
+                apart.entry(pair).or_insert(broken);
+            }
+            for c in 0..n_class {
+                let (a, b) = (xa[c], xb[c]);
+                match rel.kind {
+                    // x_a - x_b <= broken, x_b - x_a <= broken
+                    RelationKind::Together => {
+                        for (p, q) in [(a, b), (b, a)] {
+                            let mut expr = LinearExpr::empty();
+                            let Some(p) = p else { continue };
+                            expr.add(p, 1.0);
+                            if let Some(q) = q {
+                                expr.add(q, -1.0);
+                            }
+                            if let Some(broken) = broken {
+                                expr.add(broken, -1.0);
+                            }
+                            problem.add_constraint(expr, ComparisonOp::Le, 0.0);
+                        }
+                    }
+                    // x_a + x_b <= 1 + broken
+                    RelationKind::Apart => {
+                        let (Some(a), Some(b)) = (a, b) else { continue };
+                        let mut expr = LinearExpr::empty();
+                        expr.add(a, 1.0);
+                        expr.add(b, 1.0);
+                        if let Some(broken) = broken {
+                            expr.add(broken, -1.0);
+                        }
+                        problem.add_constraint(expr, ComparisonOp::Le, 1.0);
+                    }
+                }
+            }
+        }
+
+        // k members of a dislike group in n classes: at least the pairs of an even spread
+        // share a class, which the relaxation alone does not see
+        for group in &bdata.dislike_group {
+            let mut members = group.members.clone();
+            members.sort_unstable();
+            members.dedup();
+            members.retain(|sid| (*sid as usize) < bdata.students.len());
+            let pairs = members
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| members[i + 1..].iter().map(move |b| (*a, *b)))
+                .map(|pair| apart.get(&pair).copied().flatten())
+                .collect::<Vec<_>>();
+            let min_pairs = pairs_in_even_spread(members.len(), n_class);
+            if min_pairs > 0 && !pairs.is_empty() {
+                let expr = pairs
+                    .iter()
+                    .flatten()
+                    .map(|v| (*v, 1.0))
+                    .collect::<LinearExpr>();
+                problem.add_constraint(expr, ComparisonOp::Ge, min_pairs as f64);
+            }
+        }
+
+        // isolated: x_s,c - sum of the nominated friends in c <= isolated_s
+        for (s, st) in bdata.students.iter().enumerate() {
+            let mut nominations = st.nominations.clone();
+            nominations.sort_unstable();
+            nominations.dedup();
+            let friends = nominations
+                .iter()
+                .filter_map(|f| x.get(*f as usize).filter(|_| *f as usize != s))
+                .collect::<Vec<_>>();
+            if friends.is_empty() {
+                continue;
+            }
+            let isolated = indicator(&mut problem, weights.isolation);
+            for c in 0..n_class {
+                let Some(xs) = x[s][c] else { continue };
+                let mut expr = LinearExpr::empty();
+                expr.add(xs, 1.0);
+                for xf in friends.iter().filter_map(|xf| xf[c]) {
+                    expr.add(xf, -1.0);
+                }
+                expr.add(isolated, -1.0);
+                problem.add_constraint(expr, ComparisonOp::Le, 0.0);
+            }
+        }
+
+        Self {
+            problem,
+            x,
+            aggregates,
+            symmetric,
+        }
+    }
+
+    /// class of each student in an integral solution
+    fn classes(&self, lp: &Solution) -> Vec<u8> {
+        self.x
+            .iter()
+            .map(|xs| {
+                (0..xs.len())
+                    .max_by(|&a, &b| {
+                        let value = |c: usize| xs[c].map_or(-1.0, |v| lp[v]);
+                        value(a).total_cmp(&value(b))
+                    })
+                    .unwrap_or(0) as u8
+            })
+            .collect()
+    }
+
+    /// `classes` with the interchangeable classes renamed in the order of their first
+    /// student, which keeps the cost and meets the symmetry breaking of [`Model::new`]
+    fn relabel(&self, classes: &[u8]) -> Vec<u8> {
+        let mut classes = classes.to_vec();
+        for group in &self.symmetric {
+            let mut order = Vec::with_capacity(group.len());
+            for c in classes.iter().filter(|c| group.contains(c)) {
+                if !order.contains(c) {
+                    order.push(*c);
+                }
+            }
+            let unused = group.iter().filter(|c| !order.contains(c)).copied();
+            order.extend(unused.collect::<Vec<_>>());
+            for c in classes.iter_mut() {
+                if let Some(k) = order.iter().position(|o| o == c) {
+                    *c = group[k];
+                }
+            }
+        }
+        classes
+    }
+}
+
+/// classes that can be exchanged without changing the model: the same capacity limits
+/// and target size, and allowed for the same students
+fn symmetric_classes(bdata: &BuilderData, ctx: &CostContext) -> Vec<Vec<u8>> {
+    let signature = |c: u8| {
+        let config = bdata.class_config(c);
+        (
+            config.capacity_min,
+            config.capacity_max,
+            ctx.target_sizes.get(c as usize).copied(),
+            bdata
+                .students
+                .iter()
+                .map(|st| bdata.allows(st, c))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let mut groups: Vec<Vec<u8>> = Vec::new();
+    for c in 0..bdata.n_class {
+        match groups
+            .iter_mut()
+            .find(|group| signature(group[0]) == signature(c))
+        {
+            Some(group) => group.push(c),
+            None => groups.push(vec![c]),
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// pairs sharing a class when `k` students are spread as evenly as possible over `n` classes
+fn pairs_in_even_spread(k: usize, n: usize) -> usize {
+    let n = n.max(1);
+    let (q, r) = (k / n, k % n);
+    let pairs = |m: usize| m * m.saturating_sub(1) / 2;
+    r * pairs(q + 1) + (n - r) * pairs(q)
+}
+
+/// deviations in seats at which the class size is exact
+const SIZE_POINTS: [f64; 12] = [
+    0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 12.0, 16.0, 24.0, 32.0,
+];
+/// deviations of the mean score, in points, where the square is touched
+const SCORE_POINTS: [f64; 9] = [0.5, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0];
+/// deviations in students where the square is touched
+const STUDENT_POINTS: [f64; 9] = [0.5, 1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0];
+
+/// piecewise linear square of a deviation v: the largest of the lines `slope * |v| + offset`
+struct Square(Vec<(f64, f64)>);
+
+impl Square {
+    /// through the squares at consecutive `points`: exact there, above in between
+    fn secants(points: &[f64]) -> Self {
+        let lines = points.windows(2).map(|p| (p[0] + p[1], -p[0] * p[1]));
+        Self(lines.collect())
+    }
+
+    /// touching the square at `points`: below it, so that small deviations cost next to nothing
+    fn tangents(points: &[f64]) -> Self {
+        Self(points.iter().map(|a| (2.0 * a, -a * a)).collect())
+    }
+
+    /// q >= slope * |terms + constant| + offset for every line, with `cost` per unit of q
+    fn add(&self, problem: &mut Problem, terms: &[(Variable, f64)], constant: f64, cost: f32) {
+        if terms.is_empty() || cost == 0.0 {
+            return;
+        }
+        let q = problem.add_var(cost as f64, (0.0, f64::INFINITY));
+        for (slope, offset) in &self.0 {
+            for sign in [1.0, -1.0] {
+                let mut expr = terms
+                    .iter()
+                    .map(|(v, a)| (*v, -sign * slope * a))
+                    .collect::<LinearExpr>();
+                expr.add(q, 1.0);
+                problem.add_constraint(expr, ComparisonOp::Ge, sign * slope * constant + offset);
+            }
+        }
+    }
+}
+
+/// tolerance for a relaxed value to count as integral
+const INTEGRAL: f64 = 1e-6;
+
+/// `terms` `op` `rhs`, added to a subproblem when branching
+type Bound = (Terms, ComparisonOp, f64);
+
+/// `lp` with `bound` added. a constraint, also on single variables:
+/// [`Solution::fix_var`] can fail on feasible problems
+fn constrain(lp: Solution, (terms, op, rhs): &Bound) -> Result<Solution, Error> {
+    lp.add_constraint(terms.as_slice(), *op, *rhs)
+}
+
+/// subproblem waiting to be explored: the root with `bounds`, the relaxation gave `objective`.
+/// only the bounds are kept, a solved relaxation takes far more memory
+struct Node {
+    objective: f64,
+    bounds: Vec<Bound>,
+}
+
+/// branch and bound on the relaxation of [`Model`], one subproblem per step, so that a run
+/// can be stopped or timed out at any point with the best solution and its gap.
+/// it dives into the better child until a subproblem is pruned or solved, then goes on from
+/// the open one with the lowest bound. it starts from the assignment it was given, and
+/// branches on the class aggregates first, which lets the relaxation see their integrality,
+/// then on the students.
+pub(crate) struct Exact<'a> {
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    model: Model,
+    /// solved relaxation of the whole model, `None` before the first step
+    root: Option<Solution>,
+    /// subproblem of the current dive, solved, with its bounds
+    diving: Option<(Solution, Vec<Bound>)>,
+    open: Vec<Node>,
+    /// model objective of `best`, `None` until one is known
+    incumbent: Option<f64>,
+    /// lowest bound of the subproblems left out within the gap tolerance
+    pruned: Option<f64>,
+    best: AssignResult,
+}
+
+impl<'a> Exact<'a> {
+    pub(crate) fn new(bdata: &'a BuilderData, ctx: &'a CostContext, initial: AssignResult) -> Self {
+        Self {
+            bdata,
+            ctx,
+            model: Model::new(bdata, ctx),
+            root: None,
+            diving: None,
+            open: Vec::new(),
+            incumbent: None,
+            pruned: None,
+            best: initial,
+        }
+    }
+
+    /// lowest model objective any assignment can reach, as far as known
+    fn lower_bound(&self) -> Option<f64> {
+        let diving = self.diving.as_ref().map(|(lp, _)| lp.objective());
+        let open = self.open.iter().map(|node| node.objective);
+        self.incumbent
+            .into_iter()
+            .chain(self.pruned)
+            .chain(diving)
+            .chain(open)
+            .min_by(f64::total_cmp)
+    }
+
+    /// relative gap between the best solution and the lower bound
+    fn gap(&self) -> Option<f64> {
+        let incumbent = self.incumbent?;
+        let bound = self.lower_bound()?;
+        Some(((incumbent - bound) / incumbent.abs().max(1e-9)).max(0.0))
+    }
+
+    /// true if no solution above `bound` can improve the best by more than the gap tolerance.
+    /// the bound is kept in the gap
+    fn prunes(&mut self, bound: f64) -> bool {
+        let Some(incumbent) = self.incumbent else {
+            return false;
+        };
+        let tolerance = self.bdata.exact.gap_tolerance as f64 * incumbent.abs();
+        let prunes = bound >= incumbent - tolerance.max(INTEGRAL);
+        if prunes && bound < incumbent {
+            self.pruned = Some(self.pruned.map_or(bound, |pruned| pruned.min(bound)));
+        }
+        prunes
+    }
+
+    /// the assignment of `lp` as the best solution if the model finds it better
+    fn offer(&mut self, lp: &Solution) {
+        let objective = lp.objective();
+        if self
+            .incumbent
+            .is_some_and(|incumbent| objective >= incumbent)
+        {
+            return;
+        }
+        self.incumbent = Some(objective);
+        let mut best = AssignResult::from_classes(self.bdata, &self.model.classes(lp));
+        best.cal_overall_cost(self.bdata, self.ctx);
+        self.best = best;
+    }
+
+    /// the starting assignment as the first solution, if the model allows it
+    fn start_from_initial(&mut self, root: &Solution) -> Result<(), Error> {
+        let n_students = self.bdata.students.len();
+        let classes = self.model.relabel(&self.best.classes(n_students));
+        let mut lp = root.clone();
+        for (xs, c) in self.model.x.iter().zip(classes) {
+            let Some(x) = xs.get(c as usize).copied().flatten() else {
+                return Ok(());
+            };
+            lp = match constrain(lp, &(vec![(x, 1.0)], ComparisonOp::Ge, 1.0)) {
+                Err(Error::Infeasible) => return Ok(()),
+                lp => lp?,
+            };
+        }
+        self.offer(&lp);
+        Ok(())
+    }
+
+    /// the two bounds splitting `lp`, `None` if its students are all placed
+    fn branch(&self, lp: &Solution) -> Option<[Bound; 2]> {
+        let fraction = |value: f64| (value - value.round()).abs();
+        let aggregate = self
+            .model
+            .aggregates
+            .iter()
+            .map(|terms| (terms, terms.iter().map(|(v, a)| lp[*v] * a).sum::<f64>()))
+            .filter(|(_, value)| fraction(*value) > INTEGRAL)
+            .max_by(|(_, a), (_, b)| fraction(*a).total_cmp(&fraction(*b)));
+        if let Some((terms, value)) = aggregate {
+            return Some([
+                (terms.clone(), ComparisonOp::Le, value.floor()),
+                (terms.clone(), ComparisonOp::Ge, value.ceil()),
+            ]);
+        }
+        let x = self
+            .model
+            .x
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|x| fraction(lp[**x]) > INTEGRAL)
+            .max_by(|a, b| fraction(lp[**a]).total_cmp(&fraction(lp[**b])))?;
+        Some([
+            (vec![(*x, 1.0)], ComparisonOp::Le, 0.0),
+            (vec![(*x, 1.0)], ComparisonOp::Ge, 1.0),
+        ])
+    }
+
+    /// the next subproblem: the current dive, else the open one with the lowest bound,
+    /// solved again from the root
+    fn next(&mut self) -> Result<Option<(Solution, Vec<Bound>)>, Error> {
+        if let Some(diving) = self.diving.take() {
+            return Ok(Some(diving));
+        }
+        let lowest = (0..self.open.len()).min_by(|&a, &b| {
+            let objective = |k: usize| self.open[k].objective;
+            objective(a).total_cmp(&objective(b))
+        });
+        let (Some(k), Some(root)) = (lowest, &self.root) else {
+            return Ok(None);
+        };
+        let node = self.open.swap_remove(k);
+        let mut lp = root.clone();
+        for bound in &node.bounds {
+            lp = constrain(lp, bound)?;
+        }
+        Ok(Some((lp, node.bounds)))
+    }
+}
+
+impl Solver for Exact<'_> {
+    fn step(&mut self, _rng: &mut StdRng) -> Result<usize, String> {
+        let failed = |error: Error| format!("Exact solver failed: {error}");
+        if self.root.is_none() {
+            let root = self.model.problem.solve().map_err(failed)?;
+            self.start_from_initial(&root).map_err(failed)?;
+            self.diving = Some((root.clone(), Vec::new()));
+            self.root = Some(root);
+            return Ok(1);
+        }
+        let Some((lp, bounds)) = self.next().map_err(failed)? else {
+            return Ok(1);
+        };
+        if self.prunes(lp.objective()) {
+            return Ok(1);
+        }
+        let Some(branches) = self.branch(&lp) else {
+            self.offer(&lp);
+            return Ok(1);
+        };
+        let mut children = Vec::with_capacity(2);
+        for bound in branches {
+            let child = match constrain(lp.clone(), &bound) {
+                Err(Error::Infeasible) => continue,
+                child => child.map_err(failed)?,
+            };
+            if !self.prunes(child.objective()) {
+                let mut child_bounds = bounds.clone();
+                child_bounds.push(bound);
+                children.push((child, child_bounds));
+            }
+        }
+        // dive into the better one, keep the other for later
+        children.sort_by(|(a, _), (b, _)| b.objective().total_cmp(&a.objective()));
+        self.diving = children.pop();
+        self.open
+            .extend(children.into_iter().map(|(lp, bounds)| Node {
+                objective: lp.objective(),
+                bounds,
+            }));
+        Ok(1)
+    }
+    fn best_objective(&self) -> (usize, f32) {
+        self.best.objective()
+    }
+    fn is_done(&self) -> bool {
+        self.root.is_some()
+            && ((self.diving.is_none() && self.open.is_empty())
+                || self
+                    .gap()
+                    .is_some_and(|gap| gap <= self.bdata.exact.gap_tolerance as f64))
+    }
+    fn into_best(self: Box<Self>) -> AssignResult {
+        let gap = self.gap();
+        let mut best = self.best;
+        best.optimality_gap = gap.map(|gap| gap as f32);
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+
+    use super::super::fixture::{builder, grade, started};
+    use super::super::solver::SolverRun;
+    use super::super::{SeparationGroup, SolverKind, StopConfig};
+    use super::*;
+
+    /// steps `exact` until it is done, at most `n_step` times
+    fn search(exact: &mut Exact<'_>, n_step: usize) {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..n_step {
+            if exact.is_done() {
+                break;
+            }
+            exact.step(&mut rng).unwrap();
+        }
+    }
+
+    #[test]
+    fn exact_solver_keeps_hard_constraints() {
+        let mut bdata = grade();
+        assert!(bdata.exact.is_small_enough(&bdata));
+        let initial = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut exact = Exact::new(&bdata, &ctx, initial);
+        search(&mut exact, 20);
+
+        let best = Box::new(exact).into_best();
+        assert_eq!(best.hard_violations, Some(0));
+        assert!(best.optimality_gap.is_some());
+        let classes = best.classes(bdata.students.len());
+        assert_eq!(classes[0], 0, "student 0 is pinned to class 0");
+        assert_eq!(classes[1], 2, "student 1 is only allowed in class 2");
+        assert_ne!(classes[11], classes[12], "11 and 12 are hard apart");
+    }
+
+    #[test]
+    fn branch_and_bound_finds_the_model_optimum() {
+        use Gender::{Female, Male};
+        let mut bdata = builder(&[Female, Male, Male, Female, Male, Male, Female, Male]);
+        let n_students = bdata.students.len();
+        for st in &mut bdata.students {
+            st.score = (30 + st.id * 13 % 60) as f32;
+            st.nominations = vec![(st.id + 3) % n_students as u32];
+        }
+        bdata.like_group = vec![vec![3, 4]];
+        bdata.dislike_group = vec![SeparationGroup::new(vec![0, 1, 2])];
+        bdata.exact.gap_tolerance = 0.0;
+        let initial = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut exact = Exact::new(&bdata, &ctx, initial);
+        search(&mut exact, 100_000);
+        assert!(exact.is_done());
+
+        // every assignment, with its students fixed in the relaxation
+        let root = exact.model.problem.solve().unwrap();
+        let optimum = (0..1_u32 << n_students)
+            .filter_map(|bits| {
+                let classes = (0..n_students)
+                    .map(|s| (bits >> s & 1) as u8)
+                    .collect::<Vec<_>>();
+                let mut lp = root.clone();
+                for (xs, c) in exact.model.x.iter().zip(exact.model.relabel(&classes)) {
+                    lp = constrain(lp, &(vec![(xs[c as usize]?, 1.0)], ComparisonOp::Ge, 1.0))
+                        .ok()?;
+                }
+                Some(lp.objective())
+            })
+            .min_by(f64::total_cmp)
+            .unwrap();
+        let found = exact.incumbent.unwrap();
+        assert!((found - optimum).abs() < 1e-6, "{found} vs {optimum}");
+        assert_eq!(exact.gap(), Some(0.0));
+    }
+
+    #[test]
+    fn stopped_search_reports_its_gap() {
+        let mut bdata = grade();
+        bdata.exact.gap_tolerance = 0.0;
+        let initial = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut exact = Exact::new(&bdata, &ctx, initial);
+        search(&mut exact, 2);
+        assert!(!exact.is_done());
+
+        let best = Box::new(exact).into_best();
+        assert_eq!(best.hard_violations, Some(0));
+        assert!(best.optimality_gap.is_some_and(|gap| gap > 0.0));
+    }
+
+    #[test]
+    fn exact_run_stops_at_the_time_limit() {
+        let bdata = BuilderData {
+            n_iteration: 1_000_000,
+            stop: StopConfig {
+                time_limit: Some(0.05),
+                ..StopConfig::default()
+            },
+            exact: ExactConfig {
+                gap_tolerance: 0.0,
+                ..ExactConfig::default()
+            },
+            ..grade()
+        };
+        let ctx = CostContext::new(&bdata);
+        let mut run = SolverRun::new(SolverKind::Exact, &bdata, &ctx);
+        let mut progress = run.progress();
+        while !run.is_finished() {
+            progress = run.advance().unwrap();
+        }
+        assert!(progress.step < 1_000_000);
+        assert!(progress.elapsed >= 0.05);
+
+        let best = run.finish();
+        assert!(best.optimality_gap.is_some_and(|gap| gap > 0.0));
+    }
+}
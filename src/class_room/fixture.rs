@@ -0,0 +1,97 @@
+use super::{
+    AnnealingConfig, AssignResult, BuilderData, ClassConfig, ClassRoom, CostWeights, ExactConfig,
+    Gender, GeneticConfig, MoveConfig, RelationKind, Relationship, SeparationGroup, SolverKind,
+    StopConfig, Student, StudentId, TabuConfig,
+};
+
+/// test grades built explicitly, not from [`BuilderData::new_demo`]:
+/// one student of each gender in `genders`, all scored 50, in 2 classes without limits.
+/// no groups, relationships or nominations.
+pub(super) fn builder(genders: &[Gender]) -> BuilderData {
+    let students = genders
+        .iter()
+        .enumerate()
+        .map(|(iid, gender)| Student::new(iid as u32, None, *gender, 50.0))
+        .collect();
+    BuilderData {
+        n_class: 2,
+        class_configs: vec![ClassConfig::default(); 2],
+        students,
+        dislike_group: Vec::new(),
+        like_group: Vec::new(),
+        relationships: Vec::new(),
+        teacher_conflicts: Vec::new(),
+        cost_weights: CostWeights::default(),
+        n_iteration: 1000,
+        stop: StopConfig::default(),
+        seed: 0,
+        annealing: AnnealingConfig::default(),
+        genetic: GeneticConfig::default(),
+        tabu: TabuConfig::default(),
+        moves: MoveConfig::default(),
+        exact: ExactConfig::default(),
+        solver: SolverKind::default(),
+        assign_result: None,
+    }
+}
+
+/// class 0 with `members`
+pub(super) fn room(bdata: &BuilderData, members: &[StudentId]) -> ClassRoom {
+    let mut room = ClassRoom::new(0);
+    for iid in members {
+        room.push(&bdata.students[*iid as usize]);
+    }
+    room
+}
+
+/// 24 students in 3 classes with one of each constraint: student 0 pinned to class 0,
+/// 1 only allowed in class 2, 2 taking two seats, like groups {3, 4, 5} and {6, 7},
+/// dislike group {8, 9, 10}, 11 hard apart from 12, 13 wanting to be with 14,
+/// and everyone nominating the next two students
+pub(super) fn grade() -> BuilderData {
+    let genders = (0..24)
+        .map(|iid| {
+            if iid % 3 == 0 {
+                Gender::Female
+            } else {
+                Gender::Male
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut bdata = builder(&genders);
+    bdata.n_class = 3;
+    bdata.resize_class_configs();
+
+    let n_students = bdata.students.len() as StudentId;
+    for st in &mut bdata.students {
+        st.score = (20 + st.id * 37 % 70) as f32;
+        st.nominations = vec![(st.id + 1) % n_students, (st.id + 2) % n_students];
+    }
+    bdata.students[0].pinned_class = Some(0);
+    bdata.students[1].excluded_classes = vec![0, 1];
+    bdata.students[2].seat_weight = 2;
+
+    bdata.like_group = vec![vec![3, 4, 5], vec![6, 7]];
+    bdata.dislike_group = vec![SeparationGroup::new(vec![8, 9, 10])];
+    bdata.relationships = vec![
+        Relationship {
+            hard: true,
+            ..Relationship::new(11, 12, RelationKind::Apart)
+        },
+        Relationship {
+            directed: true,
+            weight: 300.0,
+            ..Relationship::new(13, 14, RelationKind::Together)
+        },
+    ];
+    bdata
+}
+
+/// starting assignment of `bdata`, see [`BuilderData::init`]
+pub(super) fn started(bdata: &mut BuilderData) -> AssignResult {
+    bdata.init();
+    bdata
+        .assign_result
+        .clone()
+        .expect("init sets the assignment")
+}
@@ -0,0 +1,276 @@
+use rand::seq::{IndexedRandom as _, SliceRandom as _};
+use rand::{Rng, rngs::StdRng};
+
+use super::{AssignResult, BuilderData, CostContext, Solver, StudentId};
+
+/// genetic solver settings, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct GeneticConfig {
+    pub(crate) population: usize,
+    /// best individuals copied unchanged into the next generation, at least one:
+    /// the best of a run is never lost
+    pub(crate) elite: usize,
+    /// individuals compared to select one parent
+    pub(crate) tournament: usize,
+    /// random swaps applied to each child
+    pub(crate) mutation_swaps: usize,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        Self {
+            population: 40,
+            elite: 2,
+            tournament: 3,
+            mutation_swaps: 2,
+        }
+    }
+}
+
+/// one assignment as the class of each student, by id
+#[derive(Debug, Clone)]
+pub(crate) struct Individual {
+    pub(crate) classes: Vec<u8>,
+    pub(crate) assign: AssignResult,
+}
+
+impl Individual {
+    fn new(classes: Vec<u8>, bdata: &BuilderData, ctx: &CostContext) -> Self {
+        let mut assign = AssignResult::from_classes(bdata, &classes);
+        assign.cal_overall_cost(bdata, ctx);
+        Self { classes, assign }
+    }
+}
+
+/// population of one genetic run, sorted best first
+pub(crate) struct Population<'a> {
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    config: GeneticConfig,
+    individuals: Vec<Individual>,
+}
+
+impl<'a> Population<'a> {
+    /// `initial` and heavily mutated copies of it
+    pub(crate) fn new(
+        bdata: &'a BuilderData,
+        ctx: &'a CostContext,
+        config: &GeneticConfig,
+        initial: &AssignResult,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let config = GeneticConfig {
+            population: config.population.max(2),
+            elite: config.elite.clamp(1, config.population.max(2) - 1),
+            tournament: config.tournament.max(1),
+            ..config.clone()
+        };
+        let classes = initial.classes(bdata.students.len());
+        let individuals = (0..config.population)
+            .map(|k| {
+                let mut classes = classes.clone();
+                if k > 0 {
+                    mutate(&mut classes, bdata, bdata.students.len() / 4, rng);
+                }
+                Individual::new(classes, bdata, ctx)
+            })
+            .collect();
+
+        let mut population = Self {
+            bdata,
+            ctx,
+            config,
+            individuals,
+        };
+        population.sort();
+        population
+    }
+
+    pub(crate) fn best(&self) -> &Individual {
+        &self.individuals[0]
+    }
+
+    /// replace the population by the elite and children of tournament-selected parents.
+    /// returns the number of evaluated children.
+    pub(crate) fn evolve(&mut self, rng: &mut impl Rng) -> usize {
+        let targets = &self.ctx.target_sizes;
+        let mut next = self.individuals[..self.config.elite].to_vec();
+        while next.len() < self.config.population {
+            let parent_a = self.select(rng);
+            let parent_b = self.select(rng);
+            let mut classes = crossover(
+                &parent_a.classes,
+                &parent_b.classes,
+                self.bdata,
+                targets,
+                rng,
+            );
+            mutate(&mut classes, self.bdata, self.config.mutation_swaps, rng);
+            next.push(Individual::new(classes, self.bdata, self.ctx));
+        }
+        self.individuals = next;
+        self.sort();
+        self.config.population - self.config.elite
+    }
+
+    /// best of `tournament` random individuals
+    fn select(&self, rng: &mut impl Rng) -> &Individual {
+        self.individuals
+            .choose_multiple(rng, self.config.tournament)
+            .reduce(|best, ind| {
+                if ind.assign.is_better_than(&best.assign) {
+                    ind
+                } else {
+                    best
+                }
+            })
+            .unwrap_or(&self.individuals[0])
+    }
+
+    fn sort(&mut self) {
+        self.individuals.sort_by(|a, b| {
+            a.assign
+                .objective()
+                .partial_cmp(&b.assign.objective())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+}
+
+/// partition crossover: whole classes of a random half from `a`, then the students
+/// `b` puts in the other classes, then a repair that puts the rest where they are
+/// allowed and the most seats are free (see [`BuilderData::target_sizes`])
+pub(crate) fn crossover(
+    a: &[u8],
+    b: &[u8],
+    bdata: &BuilderData,
+    targets: &[usize],
+    rng: &mut impl Rng,
+) -> Vec<u8> {
+    let from_a = (0..bdata.n_class)
+        .map(|_| rng.random_bool(0.5))
+        .collect::<Vec<_>>();
+    let mut child = a
+        .iter()
+        .zip(b)
+        .map(|(&ca, &cb)| {
+            let inherits = |c: u8| from_a.get(c as usize).copied();
+            if inherits(ca) == Some(true) {
+                Some(ca)
+            } else if inherits(cb) == Some(false) {
+                Some(cb)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut seats = vec![0; bdata.n_class as usize];
+    for (st, class_id) in bdata.students.iter().zip(&child) {
+        if let Some(c) = class_id {
+            seats[*c as usize] += st.seat_weight as usize;
+        }
+    }
+    let mut orphans = (0..child.len() as StudentId)
+        .filter(|sid| child[*sid as usize].is_none())
+        .collect::<Vec<_>>();
+    orphans.shuffle(rng);
+    for sid in orphans {
+        let st = &bdata.students[sid as usize];
+        let free = |c: u8| targets[c as usize] as isize - seats[c as usize] as isize;
+        let class_id = (0..bdata.n_class)
+            .filter(|&c| bdata.allows(st, c))
+            .max_by_key(|&c| free(c))
+            .or_else(|| (0..bdata.n_class).max_by_key(|&c| free(c)))
+            .unwrap_or(a[sid as usize]);
+        seats[class_id as usize] += st.seat_weight as usize;
+        child[sid as usize] = Some(class_id);
+    }
+    child
+        .into_iter()
+        .zip(a)
+        .map(|(c, ca)| c.unwrap_or(*ca))
+        .collect()
+}
+
+/// `n_swaps` random exchanges of two students of the same gender and seat weight,
+/// each allowed in the other's class: sizes, gender counts and capacities stay the same
+pub(crate) fn mutate(classes: &mut [u8], bdata: &BuilderData, n_swaps: usize, rng: &mut impl Rng) {
+    if classes.len() < 2 {
+        return;
+    }
+    for _ in 0..n_swaps {
+        let sid0 = rng.random_range(0..classes.len());
+        let sid1 = rng.random_range(0..classes.len());
+        let (st0, st1) = (&bdata.students[sid0], &bdata.students[sid1]);
+        let (c0, c1) = (classes[sid0], classes[sid1]);
+        if c0 != c1
+            && st0.gender == st1.gender
+            && st0.seat_weight == st1.seat_weight
+            && bdata.allows(st0, c1)
+            && bdata.allows(st1, c0)
+        {
+            classes.swap(sid0, sid1);
+        }
+    }
+}
+
+/// one generation per step, counted as its evaluated children
+impl Solver for Population<'_> {
+    fn step(&mut self, rng: &mut StdRng) -> Result<usize, String> {
+        Ok(self.evolve(rng))
+    }
+    fn best_objective(&self) -> (usize, f32) {
+        self.best().assign.objective()
+    }
+    fn into_best(mut self: Box<Self>) -> AssignResult {
+        self.individuals.swap_remove(0).assign
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+
+    use super::super::fixture::{grade, started};
+    use super::super::is_better;
+    use super::*;
+
+    #[test]
+    fn genetic_children_stay_valid_and_elite_survives() {
+        let mut bdata = grade();
+        let initial = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let a = initial.classes(bdata.students.len());
+        let mut b = a.clone();
+        mutate(&mut b, &bdata, 1000, &mut rng);
+        let child = crossover(&a, &b, &bdata, &ctx.target_sizes, &mut rng);
+        assert!(
+            bdata
+                .students
+                .iter()
+                .all(|st| bdata.allows(st, child[st.id as usize]))
+        );
+        // the pinned and the restricted student stay put
+        assert_eq!((child[0], child[1]), (0, 2));
+
+        // no elite configured: the best one is kept all the same
+        for elite in [2, 0] {
+            let config = GeneticConfig {
+                population: 6,
+                elite,
+                mutation_swaps: 50,
+                ..GeneticConfig::default()
+            };
+            let mut population = Population::new(&bdata, &ctx, &config, &initial, &mut rng);
+            let first = population.best().assign.objective();
+            (0..5).for_each(|_| {
+                population.evolve(&mut rng);
+            });
+            assert!(!is_better(first, population.best().assign.objective()));
+        }
+    }
+}
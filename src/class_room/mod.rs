@@ -1,24 +1,69 @@
 // use std::ops::Not;
 // #![allow(clippy::indexing_slicing)]
 
+mod annealing;
+mod constraint;
+mod construct;
+mod cost;
+mod evaluator;
+mod exact;
+#[cfg(test)]
+mod fixture;
+mod genetic;
+mod moves;
+mod relationship;
+mod solver;
+mod tabu;
+pub(crate) use annealing::{Annealing, AnnealingConfig, CoolingSchedule};
+pub(crate) use constraint::{TeacherConflict, Violation};
+pub(crate) use construct::construct;
+pub(crate) use cost::{COST_TERMS, CostContext, CostWeights};
+pub(crate) use evaluator::{Evaluator, Move, RoomStats};
+pub(crate) use exact::{Exact, ExactConfig};
+pub(crate) use genetic::{GeneticConfig, Population};
+pub(crate) use moves::{MoveConfig, sample_move};
+pub(crate) use relationship::{BrokenRelations, RelationKind, Relationship, SeparationGroup};
+pub(crate) use solver::{Progress, Solver, SolverKind, StopConfig, solve};
+pub(crate) use tabu::{TabuConfig, TabuSearch};
+
 use egui::Layout;
-use rand::seq::SliceRandom as _;
 use rand::{Rng as _, SeedableRng as _, rngs::StdRng};
 use rand_distr::{Distribution as _, Normal};
 
-#[derive(Clone)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub(crate) struct BuilderData {
     pub(crate) n_class: u8,
+    pub(crate) class_configs: Vec<ClassConfig>,
     pub(crate) students: Vec<Student>,
     //
-    pub(crate) dislike_group: Vec<Vec<StudentId>>,
+    pub(crate) dislike_group: Vec<SeparationGroup>,
     pub(crate) like_group: Vec<Vec<StudentId>>,
+    pub(crate) relationships: Vec<Relationship>,
+    //
+    pub(crate) teacher_conflicts: Vec<TeacherConflict>,
 
+    pub(crate) cost_weights: CostWeights,
     pub(crate) n_iteration: usize,
+    pub(crate) stop: StopConfig,
+    /// seeds the starting assignment and every run: same seed, data and settings, same result
+    pub(crate) seed: u64,
+    pub(crate) annealing: AnnealingConfig,
+    pub(crate) genetic: GeneticConfig,
+    pub(crate) tabu: TabuConfig,
+    pub(crate) moves: MoveConfig,
+    pub(crate) exact: ExactConfig,
+    pub(crate) solver: SolverKind,
     //
     pub(crate) assign_result: Option<AssignResult>,
 }
 
+impl Default for BuilderData {
+    fn default() -> Self {
+        Self::new_demo()
+    }
+}
+
 impl BuilderData {
     // pub(crate) fn empty_class_setup(&self) -> AssignResult {
     //     AssignResult::new(self.n_class)
@@ -33,7 +78,7 @@ impl BuilderData {
         // let mut rng = StdRng::from_os_rng();
         let normal = Normal::new(60.0_f32, 15.0).expect("get random(normal) failed");
 
-        let students = (0..n_students)
+        let mut students = (0..n_students)
             .map(|iid| {
                 let gender = if rng.random_bool(0.7) {
                     Gender::Male
@@ -46,14 +91,33 @@ impl BuilderData {
                 Student::new(iid, None, gender, score)
                 //
             })
-            .collect();
+            .collect::<Vec<_>>();
+
+        // pre-decided placements
+        students[0].pinned_class = Some(0);
+        students[1].excluded_classes = vec![0, 1];
+        students[2].seat_weight = 2;
+
+        // each student names up to three friends
+        for iid in 0..n_students {
+            let mut nominations = (0..3)
+                .map(|_| rng.random_range(0..n_students))
+                .filter(|&friend| friend != iid)
+                .collect::<Vec<_>>();
+            nominations.sort_unstable();
+            nominations.dedup();
+            students[iid as usize].nominations = nominations;
+        }
 
         let dislike_group = vec![
             //
-            vec![10, 11, 12],
-            vec![20, 21],
-            vec![31, 32, 33],
-            vec![40, 41],
+            SeparationGroup {
+                confidential: true,
+                ..SeparationGroup::new(vec![10, 11, 12])
+            },
+            SeparationGroup::new(vec![20, 21]),
+            SeparationGroup::new(vec![31, 32, 33]),
+            SeparationGroup::new(vec![40, 41]),
         ];
 
         let like_group = vec![
@@ -62,114 +126,296 @@ impl BuilderData {
             vec![60, 61],
         ];
 
+        let relationships = vec![
+            Relationship {
+                hard: true,
+                confidential: true,
+                ..Relationship::new(70, 71, RelationKind::Apart)
+            },
+            Relationship {
+                directed: true,
+                weight: 300.0,
+                ..Relationship::new(80, 81, RelationKind::Together)
+            },
+        ];
+
         Self {
             n_class,
+            class_configs: vec![ClassConfig::default(); n_class as usize],
             students,
             dislike_group,
             like_group,
+            relationships,
+            teacher_conflicts: Vec::new(),
 
+            cost_weights: CostWeights::default(),
             n_iteration: 10_000,
+            stop: StopConfig::default(),
+            seed: 0,
+            annealing: AnnealingConfig::default(),
+            genetic: GeneticConfig::default(),
+            tabu: TabuConfig::default(),
+            moves: MoveConfig::default(),
+            exact: ExactConfig::default(),
+            solver: SolverKind::default(),
 
             assign_result: None,
         }
     }
 
+    /// starting assignment of [`Self::seed`], see [`Self::initial_assignment`]
     pub(crate) fn init(&mut self) {
-        let mut rng = StdRng::from_os_rng();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let ctx = CostContext::new(self);
+        let mut assign = self.initial_assignment(&ctx, &mut rng);
+        assign.seed = Some(self.seed);
+        self.assign_result = Some(assign);
+    }
+
+    /// best of [`N_CONSTRUCTION`] greedy constructions, see [`construct`]
+    pub(crate) fn initial_assignment(&self, ctx: &CostContext, rng: &mut StdRng) -> AssignResult {
+        let mut rooms_best = AssignResult::new(self.n_class);
+        for _ in 0..N_CONSTRUCTION {
+            let mut rooms_cand = construct(self, ctx, rng);
+            rooms_cand.cal_overall_cost(self, ctx);
+            if rooms_best.overall_cost.is_none() || rooms_cand.is_better_than(&rooms_best) {
+                rooms_best = rooms_cand;
+            }
+        }
+        rooms_best
+    }
 
-        // data
-        let average_score =
-            self.students.iter().fold(0.0, |prev, st| prev + st.score) / self.students.len() as f32;
+    pub(crate) fn average_score(&self) -> f32 {
+        self.students.iter().fold(0.0, |prev, st| prev + st.score) / self.students.len() as f32
+    }
 
-        let mut students_male: Vec<_> = self
-            .students
+    /// re-evaluate the current assignment, e.g. after the cost weights changed
+    pub(crate) fn refresh_cost(&mut self) {
+        let ctx = CostContext::new(self);
+        if let Some(mut assign) = self.assign_result.take() {
+            assign.cal_overall_cost(self, &ctx);
+            self.assign_result = Some(assign);
+        }
+    }
+
+    /// explicit relationships plus the like/dislike groups imported as cliques,
+    /// without the ones of a student with themself
+    pub(crate) fn all_relationships(&self) -> Vec<Relationship> {
+        let mut relations = Relationship::cliques(&self.like_group, RelationKind::Together);
+        for group in &self.dislike_group {
+            relations.extend(
+                Relationship::clique(&group.members, RelationKind::Apart).map(|rel| Relationship {
+                    confidential: group.confidential,
+                    ..rel
+                }),
+            );
+        }
+        relations.extend(self.relationships.iter().cloned());
+        // a student paired with themself is no relationship: it would always be broken
+        relations.retain(|rel| rel.from != rel.to);
+        relations
+    }
+
+    pub(crate) fn class_config(&self, class_id: u8) -> &ClassConfig {
+        const DEFAULT: &ClassConfig = &ClassConfig {
+            name: None,
+            teacher: None,
+            location: None,
+            notes: None,
+            capacity_min: None,
+            capacity_max: None,
+        };
+        self.class_configs.get(class_id as usize).unwrap_or(DEFAULT)
+    }
+
+    /// keep one config per class after `n_class` changed
+    pub(crate) fn resize_class_configs(&mut self) {
+        self.class_configs
+            .resize(self.n_class as usize, ClassConfig::default());
+    }
+
+    /// number of seats each class should get: as equal as possible within the capacity limits
+    pub(crate) fn target_sizes(&self) -> Vec<usize> {
+        let configs = (0..self.n_class)
+            .map(|c| self.class_config(c))
+            .collect::<Vec<_>>();
+
+        let mut sizes = configs
             .iter()
-            .filter(|st| st.gender == Gender::Male)
-            .collect();
-        let mut students_female: Vec<_> = self
+            .map(|cfg| cfg.capacity_min.unwrap_or(0))
+            .collect::<Vec<_>>();
+        let n_seats = self
             .students
             .iter()
-            .filter(|st| st.gender == Gender::Female)
-            .collect();
+            .map(|st| st.seat_weight as usize)
+            .sum::<usize>();
+        let mut remaining = n_seats.saturating_sub(sizes.iter().sum());
 
-        // assign
-        //
-        //
-        students_male.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        students_female.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        while remaining > 0 {
+            let smallest = (0..sizes.len())
+                .filter(|&c| configs[c].capacity_max.is_none_or(|max| sizes[c] < max))
+                .min_by_key(|&c| sizes[c])
+                // every class is full: overfill the smallest one
+                .or_else(|| (0..sizes.len()).min_by_key(|&c| sizes[c]));
+            let Some(c) = smallest else {
+                break;
+            };
+            sizes[c] += 1;
+            remaining -= 1;
+        }
+        sizes
+    }
 
-        let mut rooms_best = AssignResult::new(self.n_class);
-        // let mut idx = 0; // big initial value
-        //
-        let mut class_ids = (0..self.n_class).collect::<Vec<_>>();
-        for _i in 0..100 {
-            // init.
-            let mut rooms_cand = AssignResult::new(self.n_class);
-
-            students_male
-                .chunks(rooms_cand.number_of_classes())
-                .for_each(|stus| {
-                    class_ids.shuffle(&mut rng);
-                    class_ids
-                        .iter()
-                        .zip(stus.iter())
-                        .for_each(|(&class_id, st)| {
-                            rooms_cand.rooms[class_id as usize]
-                                .students_male
-                                .push(st.id);
-                        });
-                });
+    /// true if `st` may be placed in class `class_id`: pins, exclusions and teacher conflicts
+    pub(crate) fn allows(&self, st: &Student, class_id: u8) -> bool {
+        st.allows_class(class_id)
+            && !self.teacher_conflicts.iter().any(|conflict| {
+                conflict.student == st.id && conflict.blocks(self.class_config(class_id))
+            })
+    }
 
-            students_female
-                .chunks(rooms_cand.number_of_classes())
-                .for_each(|stus| {
-                    class_ids.shuffle(&mut rng);
-                    class_ids
-                        .iter()
-                        .zip(stus.iter())
-                        .for_each(|(&class_id, st)| {
-                            rooms_cand.rooms[class_id as usize]
-                                .students_female
-                                .push(st.id);
-                        });
-                });
+    /// true if moving student `sid` from `room_from` to `room_to` keeps pins and exclusions,
+    /// and takes no class further out of its capacity, see [`Self::capacity_allows`].
+    /// `seats_from` and `seats_to` are the current seats of the two rooms
+    pub(crate) fn can_move(
+        &self,
+        room_from: &ClassRoom,
+        seats_from: usize,
+        sid: StudentId,
+        room_to: &ClassRoom,
+        seats_to: usize,
+    ) -> bool {
+        let st = &self.students[sid as usize];
+        self.allows(st, room_to.number)
+            // a class never becomes empty
+            && room_from.number_of_students() > 1
+            && self.capacity_allows(
+                room_from.number,
+                seats_from,
+                seats_from - st.seat_weight as usize,
+            )
+            && self.capacity_allows(room_to.number, seats_to, seats_to + st.seat_weight as usize)
+    }
 
-            //
-            //
-            rooms_cand.cal_overall_cost(self, average_score);
-            //
-            //
+    /// true if exchanging student `sid0` of `room0` and `sid1` of `room1` keeps pins and
+    /// exclusions, and takes no class further out of its capacity.
+    /// `seats0` and `seats1` are the current seats of the two rooms
+    pub(crate) fn can_swap(
+        &self,
+        room0: &ClassRoom,
+        seats0: usize,
+        sid0: StudentId,
+        room1: &ClassRoom,
+        seats1: usize,
+        sid1: StudentId,
+    ) -> bool {
+        let st0 = &self.students[sid0 as usize];
+        let st1 = &self.students[sid1 as usize];
+        if !self.allows(st0, room1.number) || !self.allows(st1, room0.number) {
+            return false;
+        }
+        if st0.seat_weight == st1.seat_weight {
+            return true;
+        }
+        let after0 = seats0 + st1.seat_weight as usize - st0.seat_weight as usize;
+        let after1 = seats1 + st0.seat_weight as usize - st1.seat_weight as usize;
+        self.capacity_allows(room0.number, seats0, after0)
+            && self.capacity_allows(room1.number, seats1, after1)
+    }
 
-            if rooms_best.overall_cost.is_none()
-                || rooms_cand.overall_cost.unwrap() < rooms_best.overall_cost.unwrap()
-            {
-                rooms_best = rooms_cand;
-                // idx = i;
-            }
+    pub(crate) fn seats_within_capacity(&self, class_id: u8, seats: usize) -> bool {
+        self.capacity_excess(class_id, seats) == 0
+    }
+
+    /// seats below the minimum or above the maximum capacity of class `class_id`
+    pub(crate) fn capacity_excess(&self, class_id: u8, seats: usize) -> usize {
+        let config = self.class_config(class_id);
+        config
+            .capacity_min
+            .map_or(0, |min| min.saturating_sub(seats))
+            + config
+                .capacity_max
+                .map_or(0, |max| seats.saturating_sub(max))
+    }
+
+    /// true if class `class_id` going from `before` to `after` seats stays within its
+    /// capacity or gets no further out of it: a class outside can be brought back step by step
+    pub(crate) fn capacity_allows(&self, class_id: u8, before: usize, after: usize) -> bool {
+        self.capacity_excess(class_id, after) <= self.capacity_excess(class_id, before)
+    }
+}
+
+/// randomised greedy constructions tried by [`BuilderData::init`]
+const N_CONSTRUCTION: usize = 10;
+
+/// lexicographic comparison of two (hard violations, soft cost) objectives
+pub(crate) fn is_better(objective: (usize, f32), other: (usize, f32)) -> bool {
+    let (hard, soft) = objective;
+    let (other_hard, other_soft) = other;
+    hard < other_hard || (hard == other_hard && soft < other_soft)
+}
+
+/// capacity limits count seats, see [`Student::seat_weight`]
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct ClassConfig {
+    pub(crate) name: Option<String>,
+    pub(crate) teacher: Option<String>,
+    pub(crate) location: Option<String>,
+    pub(crate) notes: Option<String>,
+
+    pub(crate) capacity_min: Option<usize>,
+    pub(crate) capacity_max: Option<usize>,
+}
+
+impl ClassConfig {
+    pub(crate) fn display_name(&self, class_id: u8) -> String {
+        match &self.name {
+            Some(name) if !name.is_empty() => name.clone(),
+            _ => format!("Class #{class_id}"),
         }
-        // println!("{idx}");
+    }
 
-        self.assign_result = Some(rooms_best);
+    /// "teacher: .. / room: .." (only the ones set)
+    pub(crate) fn details(&self) -> String {
+        [("teacher", &self.teacher), ("room", &self.location)]
+            .into_iter()
+            .filter_map(|(label, value)| {
+                value
+                    .as_ref()
+                    .filter(|v| !v.is_empty())
+                    .map(|v| format!("{label}: {v}"))
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
     }
 }
 
 pub(crate) type StudentId = u32;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub(crate) struct Student {
     pub(crate) id: StudentId,
     pub(crate) name: Option<String>,
     pub(crate) note: Option<String>,
     pub(crate) gender: Gender,
     pub(crate) score: f32,
+    /// placement decided beforehand: must be in this class
+    #[serde(default)]
+    pub(crate) pinned_class: Option<u8>,
+    /// must not be in any of these classes
+    #[serde(default)]
+    pub(crate) excluded_classes: Vec<u8>,
+    /// number of seats counted toward class size (e.g. 2 or 3 for a student with an IEP)
+    #[serde(default = "default_seat_weight")]
+    pub(crate) seat_weight: u8,
+    /// friends named by the student (up to three), at least one should be in the same class
+    #[serde(default)]
+    pub(crate) nominations: Vec<StudentId>,
+}
+
+fn default_seat_weight() -> u8 {
+    1
 }
 
 impl Student {
@@ -185,11 +431,26 @@ impl Student {
             gender,
             note: None,
             score,
+            pinned_class: None,
+            excluded_classes: Vec::new(),
+            seat_weight: 1,
+            nominations: Vec::new(),
         }
     }
+
+    /// true if the student may be placed in class `class_id`
+    pub(crate) fn allows_class(&self, class_id: u8) -> bool {
+        self.pinned_class.is_none_or(|c| c == class_id)
+            && !self.excluded_classes.contains(&class_id)
+    }
+
+    /// true if the placement is restricted by a pin or an exclusion
+    pub(crate) fn is_locked(&self) -> bool {
+        self.pinned_class.is_some() || !self.excluded_classes.is_empty()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) enum Gender {
     Male,
     Female,
@@ -203,10 +464,9 @@ impl Gender {
         }
     }
 }
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct ClassRoom {
     pub(crate) number: u8,
-    // name: Option<String>,
     pub(crate) students_male: Vec<StudentId>,
     pub(crate) students_female: Vec<StudentId>,
 
@@ -215,7 +475,21 @@ pub(crate) struct ClassRoom {
 
     pub(crate) dislike_count: Option<usize>,
     pub(crate) like_count: Option<usize>,
+    /// broken pairwise relationships starting in this room
+    pub(crate) broken_relations: Option<usize>,
+    #[serde(default)]
+    pub(crate) relations: Option<BrokenRelations>,
+    /// students without any of their nominated friends in this room
+    pub(crate) isolated_count: Option<usize>,
+    /// broken hard constraints: hard relationships, misplaced students, capacity
+    pub(crate) hard_violations: Option<usize>,
 
+    pub(crate) seats: Option<usize>,
+    pub(crate) target_size: Option<usize>,
+
+    /// weighted cost of each of [`COST_TERMS`] for this room
+    #[serde(default)]
+    pub(crate) cost_terms: Vec<f32>,
     pub(crate) cost: Option<f32>,
 }
 
@@ -229,198 +503,201 @@ impl ClassRoom {
             score_variance: None,
             dislike_count: None,
             like_count: None,
+            broken_relations: None,
+            relations: None,
+            isolated_count: None,
+            hard_violations: None,
+            seats: None,
+            target_size: None,
+            cost_terms: Vec::new(),
             cost: None,
         }
     }
+    fn push(&mut self, st: &Student) {
+        match st.gender {
+            Gender::Male => self.students_male.push(st.id),
+            Gender::Female => self.students_female.push(st.id),
+        }
+    }
+    /// all members, males first
+    pub(crate) fn students(&self) -> impl Iterator<Item = &StudentId> {
+        self.students_male.iter().chain(self.students_female.iter())
+    }
+    pub(crate) fn contains(&self, iid: StudentId) -> bool {
+        self.students_male.contains(&iid) || self.students_female.contains(&iid)
+    }
     fn is_empty(&self) -> bool {
         self.students_male.is_empty() && self.students_female.is_empty()
     }
-    fn number_of_students(&self) -> usize {
+    pub(crate) fn number_of_students(&self) -> usize {
         self.students_male.len() + self.students_female.len()
     }
-    fn cal_score_average(&self, students: &[Student]) -> Option<f32> {
-        if self.is_empty() {
-            return None;
-        }
-
-        let male_average = {
-            let score_sum = self
-                .students_male
-                .iter()
-                .map(|iid| students[*iid as usize].score)
-                .fold(0.0, |sum, score| sum + score);
-            score_sum / self.students_male.len() as f32
-        };
-        let female_average = {
-            let score_sum = self
-                .students_female
-                .iter()
-                .map(|iid| students[*iid as usize].score)
-                .fold(0.0, |sum, score| sum + score);
-            score_sum / self.students_female.len() as f32
-        };
-        Some(
-            (male_average * self.students_male.len() as f32
-                + female_average * self.students_female.len() as f32)
-                / ((self.students_male.len() + self.students_female.len()) as f32),
-        )
-    }
-    fn cal_score_variance(&self, students: &[Student], average_score: f32) -> Option<f32> {
-        if self.is_empty() {
-            return None;
-        }
-        let sum = self
-            .students_male
-            .iter()
-            .chain(self.students_female.iter())
-            .map(|iid| (students.get(*iid as usize).unwrap().score - average_score).powi(2))
-            .fold(0.0, |sum, score| sum + score);
-        Some(sum / (self.number_of_students() as f32))
+    /// weighted size: sum of [`Student::seat_weight`]
+    pub(crate) fn number_of_seats(&self, students: &[Student]) -> usize {
+        self.students()
+            .map(|iid| students[*iid as usize].seat_weight as usize)
+            .sum()
     }
-    pub(crate) fn update(&mut self, bdata: &BuilderData, grade_average_score: f32) {
-        self.score_average = self.cal_score_average(&bdata.students);
-
-        self.score_variance = self
-            .score_average
-            .and_then(|mean| self.cal_score_variance(&bdata.students, mean));
+    /// update the statistics, then the weighted cost of each room term of [`COST_TERMS`]
+    pub(crate) fn update(&mut self, bdata: &BuilderData, ctx: &CostContext) -> RoomStats {
+        let stats = RoomStats::new(self, bdata, ctx);
+        self.score_average = stats.mean();
+        self.score_variance = stats.variance();
 
         self.like_count = Some(self.count_like(bdata));
         self.dislike_count = Some(self.count_dislike(bdata));
+        self.broken_relations = Some(stats.relations.count);
+        self.relations = Some(stats.relations);
+        self.isolated_count = Some(stats.isolated);
+        self.seats = Some(stats.seats);
+        self.target_size = ctx.target_sizes.get(self.number as usize).copied();
+        self.hard_violations = Some(stats.hard_violations(bdata));
 
-        self.cost = self
-            .score_average
-            .zip(self.score_variance)
-            .zip(self.like_count)
-            .zip(self.dislike_count)
-            .map(|(((mean, var), n_like), n_dislike)| {
-                (mean - grade_average_score).powi(2) + var - (n_like as f32 * 1000.0)
-                    + (n_dislike as f32) * 1000.0
-            });
+        self.cost_terms = stats.term_costs(bdata, ctx).collect();
+        self.cost = (!self.is_empty()).then(|| self.cost_terms.iter().sum());
+        stats
     }
 
-    pub(crate) fn print_layout(&self, students: &[Student]) {
+    pub(crate) fn print_layout(&self, students: &[Student], config: &ClassConfig) {
         let empty = String::new();
-        self.students_male
-            .iter()
-            .chain(self.students_female.iter())
-            .enumerate()
-            .for_each(|(i, sid)| {
-                let col = i % 5;
-                let st = &students[*sid as usize];
-                let name = st.name.as_ref().unwrap_or(&empty);
-                if col == 0 {
-                    print!(
-                        "  {:3}({:5.1} {}){} ",
-                        st.id,
-                        st.score,
-                        st.gender.as_str(),
-                        name
-                    );
-                } else {
-                    print!(
-                        "| {:3}({:5.1} {}){} ",
-                        st.id,
-                        st.score,
-                        st.gender.as_str(),
-                        name
-                    );
-                }
-                if col == 4 {
-                    println!();
-                }
-            });
+        println!("{}  {}", config.display_name(self.number), config.details());
+        if let Some(notes) = &config.notes {
+            println!("  {notes}");
+        }
+        self.students().enumerate().for_each(|(i, sid)| {
+            let col = i % 5;
+            let st = &students[*sid as usize];
+            let name = st.name.as_ref().unwrap_or(&empty);
+            if col == 0 {
+                print!(
+                    "  {:3}({:5.1} {}){} ",
+                    st.id,
+                    st.score,
+                    st.gender.as_str(),
+                    name
+                );
+            } else {
+                print!(
+                    "| {:3}({:5.1} {}){} ",
+                    st.id,
+                    st.score,
+                    st.gender.as_str(),
+                    name
+                );
+            }
+            if col == 4 {
+                println!();
+            }
+        });
         println!();
     }
 
-    pub(crate) fn ui_layout(&self, ui: &mut egui::Ui, students: &[Student]) {
+    pub(crate) fn ui_layout(&self, ui: &mut egui::Ui, students: &[Student], config: &ClassConfig) {
         let empty = String::from("");
         const N_COL: usize = 5;
 
         ui.push_id(self.number, |ui| {
-            ui.label(format!("Class #{}", self.number));
+            ui.horizontal(|ui| {
+                let response = ui.strong(config.display_name(self.number));
+                if let Some(notes) = &config.notes {
+                    response.on_hover_text(notes);
+                }
+                ui.label(config.details());
+            });
             ui.group(|ui| {
                 egui::Grid::new("class#{} layout")
                     .num_columns(N_COL + 2)
                     .striped(true)
                     .show(ui, |ui| {
-                        self.students_male
-                            .iter()
-                            .chain(self.students_female.iter())
-                            .enumerate()
-                            .for_each(|(i, iid)| {
-                                let st = &students[*iid as usize];
-                                let name = st.name.as_ref().unwrap_or(&empty);
-
-                                if i % N_COL == 0 {
-                                    ui.allocate_space(egui::vec2(0.0, 0.0));
-                                    // ui.label("");
-                                }
-
-                                ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
-                                    ui.label(format!(
-                                        "{:4}({:5.1} {}){}",
-                                        st.id,
-                                        st.score,
-                                        st.gender.as_str(),
-                                        name
-                                    ));
-                                });
-
-                                // ui.allocate_space(egui::vec2(1.0, 0.0)); // 남은 가로 공간 다 먹음
-                                //
-                                if i % N_COL == N_COL - 1 {
-                                    ui.allocate_space(egui::vec2(0.0, 0.0));
-                                    // ui.label("");
-                                    ui.end_row();
-                                }
+                        self.students().enumerate().for_each(|(i, iid)| {
+                            let st = &students[*iid as usize];
+                            let name = st.name.as_ref().unwrap_or(&empty);
+
+                            if i % N_COL == 0 {
+                                ui.allocate_space(egui::vec2(0.0, 0.0));
+                                // ui.label("");
+                            }
+
+                            ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.label(format!(
+                                    "{}{:4}({:5.1} {}){}",
+                                    if st.is_locked() { "📌" } else { "" },
+                                    st.id,
+                                    st.score,
+                                    st.gender.as_str(),
+                                    name
+                                ));
                             });
+
+                            // ui.allocate_space(egui::vec2(1.0, 0.0)); // 남은 가로 공간 다 먹음
+                            //
+                            if i % N_COL == N_COL - 1 {
+                                ui.allocate_space(egui::vec2(0.0, 0.0));
+                                // ui.label("");
+                                ui.end_row();
+                            }
+                        });
                     });
             });
         });
     }
 
     pub(crate) fn count_dislike(&self, data: &BuilderData) -> usize {
-        if self.is_empty() {
-            return 0;
-        }
-
-        let mut count = 0;
-        for group in &data.dislike_group {
-            let occ = group
-                .iter()
-                .filter(|iid| self.students_male.contains(iid))
-                .count();
-            if occ > 1 {
-                count += occ - 1;
-            }
-        }
-        count
+        self.count_group_members(&data.dislike_group)
     }
     pub(crate) fn count_like(&self, data: &BuilderData) -> usize {
+        self.count_group_members(&data.like_group)
+    }
+    /// students who nominated friends but got none of them in this room
+    pub(crate) fn count_isolated(&self, students: &[Student]) -> usize {
+        self.students()
+            .filter(|iid| self.is_isolated(&students[**iid as usize]))
+            .count()
+    }
+    fn is_isolated(&self, st: &Student) -> bool {
+        !st.nominations.is_empty() && !st.nominations.iter().any(|friend| self.contains(*friend))
+    }
+    /// members of each group beyond the first found in this room, of any gender
+    fn count_group_members<G: AsRef<[StudentId]>>(&self, groups: &[G]) -> usize {
         if self.is_empty() {
             return 0;
         }
 
-        let mut count = 0;
-        for group in &data.like_group {
-            let occ = group
-                .iter()
-                .filter(|iid| self.students_male.contains(iid))
-                .count();
-            if occ > 1 {
-                count += occ - 1;
-            }
-        }
-        count
+        groups
+            .iter()
+            .map(|group| {
+                group
+                    .as_ref()
+                    .iter()
+                    .filter(|iid| self.contains(**iid))
+                    .count()
+                    .saturating_sub(1)
+            })
+            .sum()
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct AssignResult {
     pub(crate) rooms: Vec<ClassRoom>,
     // pub(crate) students: Vec<Student>,
     // pub(crate) dislike: Vec<Vec<StudentId>>,
+    /// weighted cost of each of [`COST_TERMS`], summed over the rooms
+    #[serde(default)]
+    pub(crate) cost_terms: Vec<f32>,
+    /// soft cost, only compared between results with the same number of hard violations
     pub(crate) overall_cost: Option<f32>,
+    #[serde(default)]
+    pub(crate) hard_violations: Option<usize>,
+    /// exact solver only: relative gap between the solution and the proven lower bound
+    #[serde(default)]
+    pub(crate) optimality_gap: Option<f32>,
+    /// [`BuilderData::seed`] it was produced with
+    #[serde(default)]
+    pub(crate) seed: Option<u64>,
+    /// solver it was produced by, `None` for a starting assignment
+    #[serde(default)]
+    pub(crate) solver: Option<SolverKind>,
 }
 
 impl AssignResult {
@@ -429,51 +706,219 @@ impl AssignResult {
         Self {
             rooms,
             // dislike,
+            cost_terms: Vec::new(),
             overall_cost: None,
+            hard_violations: None,
+            optimality_gap: None,
+            seed: None,
+            solver: None,
+        }
+    }
+    /// students placed by class id, see [`Self::classes`]
+    pub(crate) fn from_classes(bdata: &BuilderData, classes: &[u8]) -> Self {
+        let mut assign = Self::new(bdata.n_class);
+        for (st, class_id) in bdata.students.iter().zip(classes) {
+            if let Some(room) = assign.rooms.get_mut(*class_id as usize) {
+                room.push(st);
+            }
         }
+        assign
     }
-    pub(crate) fn number_of_classes(&self) -> usize {
-        self.rooms.len()
+    /// class id of each student, by id (0 if in no class)
+    pub(crate) fn classes(&self, n_students: usize) -> Vec<u8> {
+        let mut classes = vec![0; n_students];
+        for room in &self.rooms {
+            for iid in room.students() {
+                classes[*iid as usize] = room.number;
+            }
+        }
+        classes
     }
 
-    pub(crate) fn cal_overall_cost(&mut self, bdata: &BuilderData, average_score: f32) {
-        self.rooms
+    /// overall cost: the sum of all [`COST_TERMS`] over the rooms and the whole assignment
+    pub(crate) fn cal_overall_cost(&mut self, bdata: &BuilderData, ctx: &CostContext) {
+        let stats = self
+            .rooms
             .iter_mut()
-            .for_each(|c| c.update(bdata, average_score));
+            .map(|c| c.update(bdata, ctx))
+            .collect::<Vec<_>>();
 
-        let var_max = self
-            .rooms
+        self.cost_terms = COST_TERMS
             .iter()
-            .map(|c| c.score_variance.unwrap())
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-        let score_variance = var_max * self.number_of_classes() as f32;
+            .enumerate()
+            .map(|(k, term)| {
+                let rooms = self
+                    .rooms
+                    .iter()
+                    .filter_map(|c| c.cost_terms.get(k))
+                    .sum::<f32>();
+                rooms + term.global_cost(&stats, bdata, ctx) * term.weight(&bdata.cost_weights)
+            })
+            .collect();
 
-        let score_average = self
-            .rooms
-            .iter()
-            .fold(0.0, |prev, c| prev + c.cost.unwrap());
+        self.overall_cost = Some(self.cost_terms.iter().sum());
+        self.hard_violations = Some(
+            self.rooms
+                .iter()
+                .map(|c| c.hard_violations.unwrap_or(0))
+                .sum(),
+        );
+    }
+
+    /// (hard violations, soft cost): hard violations rank above any soft cost
+    pub(crate) fn objective(&self) -> (usize, f32) {
+        (
+            self.hard_violations.unwrap_or(usize::MAX),
+            self.overall_cost.unwrap_or(f32::INFINITY),
+        )
+    }
+
+    /// lexicographic comparison of [`Self::objective`]
+    pub(crate) fn is_better_than(&self, other: &Self) -> bool {
+        is_better(self.objective(), other.objective())
+    }
+
+    /// roster as csv: one line per student, with the class metadata and the separations
+    /// the student is part of. confidential separations only if `include_confidential`.
+    pub(crate) fn roster_csv(&self, bdata: &BuilderData, include_confidential: bool) -> String {
+        let relations = bdata.all_relationships();
+        let mut csv = String::from(
+            "class,class_name,teacher,room,class_notes,id,name,gender,score,separated_from\n",
+        );
+        for class in &self.rooms {
+            let config = bdata.class_config(class.number);
+            for iid in class.students() {
+                let st = &bdata.students[*iid as usize];
+                let fields = [
+                    class.number.to_string(),
+                    config.display_name(class.number),
+                    config.teacher.clone().unwrap_or_default(),
+                    config.location.clone().unwrap_or_default(),
+                    config.notes.clone().unwrap_or_default(),
+                    st.id.to_string(),
+                    st.name.clone().unwrap_or_default(),
+                    st.gender.as_str().to_owned(),
+                    format!("{:.1}", st.score),
+                    relations
+                        .iter()
+                        .filter(|rel| rel.kind == RelationKind::Apart)
+                        .filter(|rel| include_confidential || !rel.confidential)
+                        .filter_map(|rel| {
+                            if rel.from == st.id {
+                                Some(rel.to)
+                            } else if rel.to == st.id {
+                                Some(rel.from)
+                            } else {
+                                None
+                            }
+                        })
+                        .map(|iid| iid.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ];
+                let line = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+                csv.push_str(&line.join(","));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+
+    /// index of the room with the highest cost of each term of [`COST_TERMS`],
+    /// `None` if no room has a cost for it
+    fn worst_rooms(&self) -> Vec<Option<usize>> {
+        (0..COST_TERMS.len())
+            .map(|k| {
+                self.rooms
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, c)| c.cost_terms.get(k).map(|cost| (i, *cost)))
+                    .filter(|(_, cost)| *cost > 0.0)
+                    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(i, _)| i)
+            })
+            .collect()
+    }
+
+    /// weighted cost of each term per class and in total, the worst class of each term highlighted
+    fn ui_cost_breakdown(&self, ui: &mut egui::Ui, bdata: &BuilderData) {
+        let worst = self.worst_rooms();
+
+        egui::Grid::new("cost breakdown")
+            .num_columns(COST_TERMS.len() + 2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Cost");
+                for term in COST_TERMS {
+                    ui.label(term.name()).on_hover_text(format!(
+                        "{}\nweighted by {}, lower is better",
+                        term.description(),
+                        term.weight(&bdata.cost_weights)
+                    ));
+                }
+                ui.label("Total");
+                ui.end_row();
 
-        self.overall_cost = Some(score_average + score_variance);
+                for (i, class) in self.rooms.iter().enumerate() {
+                    ui.label(bdata.class_config(class.number).display_name(class.number));
+                    for (k, worst) in worst.iter().enumerate() {
+                        let text = format!("{:.1}", class.cost_terms.get(k).unwrap_or(&0.0));
+                        if *worst == Some(i) {
+                            ui.colored_label(ui.visuals().warn_fg_color, text)
+                                .on_hover_text("worst class for this term");
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+                    ui.label(format!("{:.1}", class.cost.unwrap_or(0.0)));
+                    ui.end_row();
+                }
+
+                ui.strong("All")
+                    .on_hover_text("sum over the classes, plus the terms of the whole grade");
+                for k in 0..COST_TERMS.len() {
+                    ui.strong(format!("{:.1}", self.cost_terms.get(k).unwrap_or(&0.0)));
+                }
+                ui.strong(format!("{:.1}", self.overall_cost.unwrap_or(0.0)));
+                ui.end_row();
+            });
     }
 
-    pub(crate) fn ui_statistics(&self, ui: &mut egui::Ui) {
+    pub(crate) fn ui_statistics(&self, ui: &mut egui::Ui, bdata: &BuilderData) {
         egui::Grid::new("class stats")
             .num_columns(5)
             .striped(true)
             .show(ui, |ui| {
                 ui.label("# Class");
                 ui.label("# Stu.");
+                ui.label("# Seats");
                 ui.label("(M/F)");
                 ui.label("Mean\nScore");
                 ui.label("Stdev.");
                 ui.label("# Dis.");
                 ui.label("# Like");
+                ui.label("# Isolated")
+                    .on_hover_text("students without any nominated friend in the class");
+                ui.label("# Hard\nViol.")
+                    .on_hover_text("broken hard relationships, misplaced students, capacity");
+                ui.label("# Broken\nPairs")
+                    .on_hover_text("broken pairwise relationships (incl. group pairs)");
                 ui.label("Assign\nCost").on_hover_text("(Lower is better)");
                 ui.end_row();
                 for class in &self.rooms {
-                    ui.label(format!("No.{}", class.number));
+                    let config = bdata.class_config(class.number);
+                    let response = ui.label(config.display_name(class.number));
+                    let details = config.details();
+                    if !details.is_empty() {
+                        response.on_hover_text(details);
+                    }
                     ui.label(format!("{}", class.number_of_students()));
+                    ui.label(format!(
+                        "{}/{}",
+                        class.seats.unwrap_or(0),
+                        class.target_size.unwrap_or(0)
+                    ))
+                    .on_hover_text("weighted seats / target seats");
                     ui.label(format!(
                         "({}/{})",
                         class.students_male.len(),
@@ -483,15 +928,92 @@ impl AssignResult {
                     ui.label(format!("{:.1}", class.score_variance.unwrap_or(0.0).sqrt()));
                     ui.label(format!("{}", class.dislike_count.unwrap_or(0)));
                     ui.label(format!("{}", class.like_count.unwrap_or(0)));
+                    ui.label(format!("{}", class.isolated_count.unwrap_or(0)));
+                    ui.label(format!("{}", class.hard_violations.unwrap_or(0)));
+                    ui.label(format!("{}", class.broken_relations.unwrap_or(0)));
                     ui.label(format!("{:.1}", class.cost.unwrap_or(0.0)));
                     ui.end_row();
                 }
             });
         ui.add_space(10.0);
+        self.ui_cost_breakdown(ui, bdata);
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Isolated students:");
+            ui.strong(format!(
+                "{}",
+                self.rooms
+                    .iter()
+                    .map(|c| c.isolated_count.unwrap_or(0))
+                    .sum::<usize>()
+            ));
+        });
+        ui.horizontal(|ui| {
+            // anonymous: never which students
+            let n_confidential = bdata
+                .all_relationships()
+                .iter()
+                .filter(|rel| rel.confidential && rel.kind == RelationKind::Apart)
+                .filter(|rel| {
+                    self.rooms
+                        .iter()
+                        .any(|c| c.contains(rel.from) && c.contains(rel.to))
+                })
+                .count();
+            ui.label("Confidential separations broken:");
+            ui.strong(format!("{n_confidential}"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Hard violations:");
+            let n_hard = self.hard_violations.unwrap_or(0);
+            if n_hard > 0 {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{n_hard}"));
+            } else {
+                ui.strong("0");
+            }
+        });
         ui.horizontal(|ui| {
             ui.label("Overall cost:");
-            ui.strong(format!("{:.1}", self.overall_cost.unwrap_or(0.0)));
+            ui.strong(format!("{:.1}", self.overall_cost.unwrap_or(0.0)))
+                .on_hover_text("soft cost, compared only when the hard violations are equal");
         });
+        if let Some(seed) = self.seed {
+            ui.horizontal(|ui| {
+                ui.label("Produced by:");
+                let solver = self
+                    .solver
+                    .as_ref()
+                    .map_or("starting assignment", |kind| kind.as_str());
+                ui.strong(format!("{solver}, seed {seed}")).on_hover_text(
+                    "the same seed, data and settings give the same assignment again",
+                );
+            });
+        }
+        if let Some(gap) = self.optimality_gap {
+            ui.horizontal(|ui| {
+                ui.label("Exact solver:");
+                let status = if gap <= f32::EPSILON {
+                    "optimal".to_owned()
+                } else if gap < 1e-4 {
+                    "gap below 0.01% to the lower bound".to_owned()
+                } else {
+                    format!("gap {:.2}% to the lower bound", gap * 100.0)
+                };
+                ui.strong(status).on_hover_text(
+                    "for the linearised model (piecewise linear instead of squared deviations, \
+                     no score variance), scored above with the full cost. \
+                     a gap remains when the search stopped at a limit or at the gap tolerance",
+                );
+            });
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
     }
 }
 
@@ -526,9 +1048,296 @@ fn test() {
                 );
 
                 if false {
-                    class.print_layout(&builder_data.students);
+                    class.print_layout(
+                        &builder_data.students,
+                        builder_data.class_config(class.number),
+                    );
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::cost::CostTerm as _;
+    use super::fixture::{builder, room};
+    use super::*;
+
+    #[test]
+    fn init_keeps_pinned_and_excluded_students() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female].repeat(6));
+        bdata.n_class = 3;
+        bdata.resize_class_configs();
+        bdata.students[0].pinned_class = Some(2);
+        bdata.students[1].pinned_class = Some(2);
+        bdata.students[2].excluded_classes = vec![0, 1];
+        bdata.students[3].excluded_classes = vec![2];
+
+        for seed in 0..10 {
+            bdata.seed = seed;
+            bdata.init();
+            let assign = bdata.assign_result.as_ref().unwrap();
+            let class_of = |iid| {
+                assign
+                    .rooms
+                    .iter()
+                    .find(|r| r.contains(iid))
+                    .unwrap()
+                    .number
+            };
+            assert_eq!((class_of(0), class_of(1), class_of(2)), (2, 2, 2));
+            assert_ne!(class_of(3), 2);
+            assert_eq!(assign.hard_violations, Some(0));
+        }
+    }
+
+    #[test]
+    fn target_sizes_respect_capacity_limits() {
+        let mut bdata = builder(&[Gender::Male; 10]);
+        bdata.n_class = 3;
+        bdata.class_configs = vec![
+            ClassConfig {
+                capacity_max: Some(2),
+                ..ClassConfig::default()
+            },
+            ClassConfig {
+                capacity_min: Some(5),
+                ..ClassConfig::default()
+            },
+            ClassConfig::default(),
+        ];
+        assert_eq!(bdata.target_sizes(), vec![2, 5, 3]);
+
+        // every class full: the smallest one takes the rest
+        bdata.class_configs[1].capacity_max = Some(5);
+        bdata.class_configs[2].capacity_max = Some(2);
+        assert_eq!(bdata.target_sizes(), vec![3, 5, 2]);
+    }
+
+    #[test]
+    fn sizes_and_capacity_count_seats() {
+        let mut bdata = builder(&[Gender::Male; 4]);
+        bdata.students[0].seat_weight = 3;
+        bdata.class_configs[0].capacity_max = Some(4);
+        let ctx = CostContext::new(&bdata);
+        // 6 seats over 2 classes
+        assert_eq!(ctx.target_sizes, vec![3, 3]);
+
+        let mut assign = AssignResult::from_classes(&bdata, &[0, 0, 1, 1]);
+        assign.cal_overall_cost(&bdata, &ctx);
+        let room = &assign.rooms[0];
+        assert_eq!(
+            (
+                room.number_of_students(),
+                room.number_of_seats(&bdata.students)
+            ),
+            (2, 4)
+        );
+        assert_eq!(assign.hard_violations, Some(0));
+
+        // 5 seats for 3 students is over the max. of 4
+        let mut assign = AssignResult::from_classes(&bdata, &[0, 0, 0, 1]);
+        assign.cal_overall_cost(&bdata, &ctx);
+        assert_eq!(assign.hard_violations, Some(1));
+    }
+
+    #[test]
+    fn moves_bring_a_class_back_into_capacity() {
+        let mut bdata = builder(&[Gender::Male; 6]);
+        bdata.class_configs[0].capacity_max = Some(4);
+        bdata.class_configs[1].capacity_min = Some(4);
+        let full = room(&bdata, &[0, 1, 2, 3]);
+        let mut short = room(&bdata, &[4, 5]);
+        short.number = 1;
+
+        // 2 -> 3 seats is still below the minimum, but closer
+        assert!(bdata.can_move(&full, 4, 0, &short, 2));
+        assert!(!bdata.can_move(&short, 2, 4, &full, 4));
+
+        // both out of capacity: 5 -> 4 seats and 2 -> 3 seats
+        bdata.students[0].seat_weight = 2;
+        let over = room(&bdata, &[0, 1, 2, 3]);
+        assert!(bdata.can_swap(&over, 5, 0, &short, 2, 4));
+        // a class within its capacity stays within it
+        assert!(!bdata.capacity_allows(0, 4, 5));
+        assert!(bdata.capacity_allows(0, 6, 5));
+    }
+
+    #[test]
+    fn dislike_pair_of_females_is_counted() {
+        let mut bdata = builder(&[Gender::Female, Gender::Female, Gender::Male]);
+        bdata.dislike_group = vec![SeparationGroup::new(vec![0, 1])];
+
+        assert_eq!(room(&bdata, &[0, 1, 2]).count_dislike(&bdata), 1);
+        assert_eq!(room(&bdata, &[0, 2]).count_dislike(&bdata), 0);
+    }
+
+    #[test]
+    fn like_group_of_females_is_counted() {
+        let mut bdata = builder(&[Gender::Female, Gender::Female, Gender::Female]);
+        bdata.like_group = vec![vec![0, 1, 2]];
+
+        assert_eq!(room(&bdata, &[0, 1, 2]).count_like(&bdata), 2);
+        assert_eq!(room(&bdata, &[1, 2]).count_like(&bdata), 1);
+    }
+
+    #[test]
+    fn mixed_gender_groups_are_counted() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female, Gender::Male]);
+        bdata.dislike_group = vec![SeparationGroup::new(vec![0, 1])];
+        bdata.like_group = vec![vec![2, 3]];
+
+        let together = room(&bdata, &[0, 1, 2, 3]);
+        assert_eq!(together.count_dislike(&bdata), 1);
+        assert_eq!(together.count_like(&bdata), 1);
+
+        let apart = room(&bdata, &[0, 2]);
+        assert_eq!(apart.count_dislike(&bdata), 0);
+        assert_eq!(apart.count_like(&bdata), 0);
+    }
+
+    #[test]
+    fn groups_import_as_cliques() {
+        let relations = Relationship::cliques(&[vec![0, 1, 2], vec![3, 4]], RelationKind::Apart);
+        assert_eq!(relations.len(), 4);
+        assert!(relations.iter().all(|rel| !rel.directed && !rel.hard));
+    }
+
+    #[test]
+    fn relationship_with_oneself_is_ignored() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        let itself = Relationship {
+            hard: true,
+            ..Relationship::new(0, 0, RelationKind::Apart)
+        };
+        bdata.relationships = vec![itself.clone(), Relationship::new(0, 1, RelationKind::Apart)];
+
+        assert_eq!(bdata.all_relationships().len(), 1);
+        let broken = room(&bdata, &[0]).count_broken_relations(&[itself]);
+        assert_eq!((broken.count, broken.hard), (0, 0));
+    }
+
+    #[test]
+    fn students_without_nominated_friend_are_isolated() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female]);
+        bdata.students[0].nominations = vec![1, 2];
+        bdata.students[1].nominations = vec![2];
+
+        // 0 has friend 1, 1 misses 2, 2 nominated nobody
+        assert_eq!(room(&bdata, &[0, 1]).count_isolated(&bdata.students), 1);
+        assert_eq!(room(&bdata, &[0, 1, 2]).count_isolated(&bdata.students), 0);
+    }
+
+    #[test]
+    fn confidential_separations_are_left_out_of_roster() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female]);
+        bdata.dislike_group = vec![SeparationGroup {
+            confidential: true,
+            ..SeparationGroup::new(vec![0, 1])
+        }];
+        bdata.relationships = vec![Relationship::new(1, 2, RelationKind::Apart)];
+        let mut assign = AssignResult::new(2);
+        assign.rooms[0] = room(&bdata, &[0, 2]);
+        assign.rooms[1].push(&bdata.students[1]);
+
+        let roster = assign.roster_csv(&bdata, false);
+        let separated = |roster: &str, iid: usize| {
+            roster
+                .lines()
+                .nth(iid + 1)
+                .and_then(|line| line.rsplit(',').next())
+                .map(str::to_owned)
+        };
+        // lines are in class order: 0, 2 | 1
+        assert_eq!(separated(&roster, 0).as_deref(), Some(""));
+        assert_eq!(separated(&roster, 1).as_deref(), Some("1"));
+        assert_eq!(separated(&roster, 2).as_deref(), Some("2"));
+
+        let roster = assign.roster_csv(&bdata, true);
+        assert_eq!(separated(&roster, 0).as_deref(), Some("1"));
+        assert_eq!(separated(&roster, 2).as_deref(), Some("0 2"));
+    }
+
+    #[test]
+    fn roster_has_the_configured_class_name_and_teacher() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female]);
+        bdata.class_configs[0] = ClassConfig {
+            name: Some("Blue, 5a".to_owned()),
+            teacher: Some("Kim".to_owned()),
+            location: Some("B12".to_owned()),
+            ..ClassConfig::default()
+        };
+        let assign = AssignResult::from_classes(&bdata, &[0, 1]);
+
+        let roster = assign.roster_csv(&bdata, false);
+        let lines = roster.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("class,class_name,teacher,room,"));
+        assert!(lines[1].starts_with("0,\"Blue, 5a\",Kim,B12,,0,"));
+        // no name configured: the default one, and no teacher
+        assert!(lines[2].starts_with("1,Class #1,,,,1,"));
+        assert_eq!(bdata.class_configs[0].details(), "teacher: Kim / room: B12");
+        assert_eq!(bdata.class_configs[1].details(), "");
+    }
+
+    #[test]
+    fn cost_breakdown_per_class_marks_the_worst_class() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Male, Gender::Female]);
+        bdata.students[2].score = 20.0;
+        bdata.students[3].score = 80.0;
+        let ctx = CostContext::new(&bdata);
+        let mut assign = AssignResult::from_classes(&bdata, &[0, 0, 1, 1]);
+        assign.cal_overall_cost(&bdata, &ctx);
+
+        let term = |name: &str| COST_TERMS.iter().position(|t| t.name() == name).unwrap();
+        let (mean, variance) = (
+            term(cost::ScoreMean.name()),
+            term(cost::ScoreVariance.name()),
+        );
+        for room in &assign.rooms {
+            assert_eq!(room.cost_terms.len(), COST_TERMS.len());
+            assert_eq!(room.cost, Some(room.cost_terms.iter().sum()));
+        }
+        // both classes have the grade mean, only class 1 has a spread
+        let worst = assign.worst_rooms();
+        assert_eq!(worst[mean], None);
+        assert_eq!(worst[variance], Some(1));
+        assert_eq!(
+            assign.cost_terms[variance],
+            assign.rooms[1].cost_terms[variance]
+        );
+    }
+
+    #[test]
+    fn hard_violations_rank_above_soft_cost() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Female, Gender::Male]);
+        bdata.relationships = vec![Relationship {
+            hard: true,
+            ..Relationship::new(0, 1, RelationKind::Apart)
+        }];
+        let ctx = CostContext::new(&bdata);
+
+        let mut violating = AssignResult::new(2);
+        violating.rooms[0] = room(&bdata, &[0, 1]);
+        violating.rooms[1] = room(&bdata, &[2, 3]);
+        violating.rooms[1].number = 1;
+        violating.cal_overall_cost(&bdata, &ctx);
+
+        let mut separated = AssignResult::new(2);
+        separated.rooms[0] = room(&bdata, &[0, 2]);
+        separated.rooms[1] = room(&bdata, &[1, 3]);
+        separated.rooms[1].number = 1;
+        separated.cal_overall_cost(&bdata, &ctx);
+        // make the separated one worse on the soft side
+        separated.overall_cost = separated.overall_cost.map(|cost| cost + 1e9);
+
+        assert_eq!(violating.hard_violations, Some(1));
+        assert_eq!(separated.hard_violations, Some(0));
+        assert!(separated.is_better_than(&violating));
+
+        let report = violating.violations(&bdata);
+        assert_eq!(report.len(), 1);
+        assert!(report[0].hard);
+        assert_eq!(report[0].students, vec![0, 1]);
+    }
+}
@@ -0,0 +1,268 @@
+use rand::Rng;
+use rand::seq::{IndexedRandom as _, index};
+
+use super::{BuilderData, ClassRoom, Evaluator, Gender, Move, StudentId};
+
+/// relative frequency of each move type in local search, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct MoveConfig {
+    /// one student to another class: changes the class sizes
+    pub(crate) single: f32,
+    /// two students of the same gender exchange classes
+    pub(crate) swap: f32,
+    /// three students of the same gender rotate through three classes
+    pub(crate) rotation: f32,
+    /// a whole like group into the class of one of its members
+    pub(crate) group: f32,
+}
+
+impl Default for MoveConfig {
+    fn default() -> Self {
+        Self {
+            single: 0.3,
+            swap: 0.5,
+            rotation: 0.1,
+            group: 0.1,
+        }
+    }
+}
+
+/// random move that keeps pins, exclusions and capacities, `None` if the drawn one does not
+pub(crate) fn sample_move(
+    evaluator: &Evaluator<'_>,
+    bdata: &BuilderData,
+    config: &MoveConfig,
+    rng: &mut impl Rng,
+) -> Option<Move> {
+    let rooms = evaluator.rooms();
+    if bdata.students.is_empty() || rooms.len() < 2 {
+        return None;
+    }
+    let weights = [config.single, config.swap, config.rotation, config.group].map(|w| w.max(0.0));
+    let total = weights.iter().sum::<f32>();
+    let mut pick = if total > 0.0 {
+        rng.random_range(0.0..total)
+    } else {
+        config.single.max(0.0)
+    };
+    let kind = weights
+        .iter()
+        .position(|w| {
+            pick -= w;
+            pick < 0.0
+        })
+        .unwrap_or(1);
+
+    match kind {
+        0 => {
+            let (room_from, room_to) = two_rooms(rooms, rng);
+            let sid = *room_from.students().collect::<Vec<_>>().choose(rng)?;
+            bdata
+                .can_move(
+                    room_from,
+                    evaluator.seats(room_from.number),
+                    *sid,
+                    room_to,
+                    evaluator.seats(room_to.number),
+                )
+                .then_some(Move::Student(*sid, room_to.number))
+        }
+        1 => {
+            let (room0, room1) = two_rooms(rooms, rng);
+            let gender = random_gender(rng);
+            let sid0 = *members(room0, gender).choose(rng)?;
+            let sid1 = *members(room1, gender).choose(rng)?;
+            bdata
+                .can_swap(
+                    room0,
+                    evaluator.seats(room0.number),
+                    sid0,
+                    room1,
+                    evaluator.seats(room1.number),
+                    sid1,
+                )
+                .then_some(Move::Swap(sid0, sid1))
+        }
+        2 => {
+            if rooms.len() < 3 {
+                return None;
+            }
+            let picked = index::sample(rng, rooms.len(), 3);
+            let gender = random_gender(rng);
+            let rotated = picked
+                .iter()
+                .map(|c| members(&rooms[c], gender).choose(rng).copied())
+                .collect::<Option<Vec<_>>>()?;
+            // each student goes to the class of the next one
+            let fits = (0..3).all(|k| {
+                let (st, next, prev) = (
+                    &bdata.students[rotated[k] as usize],
+                    &rooms[picked.index((k + 1) % 3)],
+                    &bdata.students[rotated[(k + 2) % 3] as usize],
+                );
+                let class_id = rooms[picked.index(k)].number;
+                let seats = evaluator.seats(class_id);
+                bdata.allows(st, next.number)
+                    && bdata.capacity_allows(
+                        class_id,
+                        seats,
+                        seats + prev.seat_weight as usize - st.seat_weight as usize,
+                    )
+            });
+            fits.then_some(Move::Rotate(rotated[0], rotated[1], rotated[2]))
+        }
+        _ => {
+            let group = rng.random_range(0..bdata.like_group.len().max(1));
+            let members = bdata
+                .like_group
+                .get(group)?
+                .iter()
+                .copied()
+                .filter(|sid| (*sid as usize) < bdata.students.len())
+                .collect::<Vec<_>>();
+            let to = evaluator.class_of(*members.choose(rng)?);
+            let moving = members
+                .into_iter()
+                .filter(|sid| evaluator.class_of(*sid) != to)
+                .collect::<Vec<_>>();
+            if moving.is_empty() {
+                return None;
+            }
+            group_fits(evaluator, bdata, &moving, to).then_some(Move::Group(group, to))
+        }
+    }
+}
+
+/// two different random rooms
+fn two_rooms<'r>(rooms: &'r [ClassRoom], rng: &mut impl Rng) -> (&'r ClassRoom, &'r ClassRoom) {
+    let from = rng.random_range(0..rooms.len());
+    let to = (from + rng.random_range(1..rooms.len())) % rooms.len();
+    (&rooms[from], &rooms[to])
+}
+
+fn random_gender(rng: &mut impl Rng) -> Gender {
+    *[Gender::Male, Gender::Female]
+        .choose(rng)
+        .unwrap_or(&Gender::Male)
+}
+
+fn members(room: &ClassRoom, gender: Gender) -> &[StudentId] {
+    match gender {
+        Gender::Male => &room.students_male,
+        Gender::Female => &room.students_female,
+    }
+}
+
+/// true if `moving` may all go to class `to`: allowed there, no class further out of
+/// its capacity, and no class they leave becomes empty
+fn group_fits(
+    evaluator: &Evaluator<'_>,
+    bdata: &BuilderData,
+    moving: &[StudentId],
+    to: u8,
+) -> bool {
+    // (class, change in seats, students leaving) of the classes involved
+    let mut changes = vec![(to, 0isize, 0usize)];
+    for sid in moving {
+        let st = &bdata.students[*sid as usize];
+        if !bdata.allows(st, to) {
+            return false;
+        }
+        let from = evaluator.class_of(*sid);
+        let k = match changes.iter().position(|(c, _, _)| *c == from) {
+            Some(k) => k,
+            None => {
+                changes.push((from, 0, 0));
+                changes.len() - 1
+            }
+        };
+        changes[k].1 -= st.seat_weight as isize;
+        changes[k].2 += 1;
+        changes[0].1 += st.seat_weight as isize;
+    }
+    changes.iter().all(|&(c, delta, leaving)| {
+        let before = evaluator.seats(c);
+        delta == 0
+            || (leaving < evaluator.rooms()[c as usize].number_of_students()
+                && bdata.capacity_allows(c, before, before.saturating_add_signed(delta)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng as _, rngs::StdRng};
+
+    use super::super::CostContext;
+    use super::super::fixture::{grade, started};
+    use super::*;
+
+    #[test]
+    fn sampled_moves_keep_constraints_and_revert() {
+        let mut bdata = grade();
+        let assign = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut evaluator = Evaluator::new(&bdata, &ctx, assign);
+        let mut rng = StdRng::seed_from_u64(4);
+        let n_students = bdata.students.len();
+        // split a like group, so that there is a group move to sample
+        evaluator.apply(Move::Student(4, (evaluator.class_of(3) + 1) % 3));
+
+        let only = |single, rotation, group| MoveConfig {
+            single,
+            swap: 0.0,
+            rotation,
+            group,
+        };
+        let cases = [
+            (only(1.0, 0.0, 0.0), "Student"),
+            (only(0.0, 1.0, 0.0), "Rotate"),
+            (only(0.0, 0.0, 1.0), "Group"),
+        ];
+        for (config, kind) in cases {
+            let mv = (0..1000)
+                .find_map(|_| sample_move(&evaluator, &bdata, &config, &mut rng))
+                .unwrap();
+            assert!(format!("{mv:?}").starts_with(kind), "{mv:?}");
+            let before = evaluator.assignment().classes(n_students);
+            let undo = evaluator.apply(mv);
+            let after = evaluator.assignment().classes(n_students);
+            let moved = mv.students(&bdata);
+            for sid in 0..n_students {
+                if !moved.contains(&(sid as StudentId)) {
+                    assert_eq!(before[sid], after[sid]);
+                }
+            }
+            if let Move::Group(group, to) = mv {
+                assert!(
+                    bdata.like_group[group]
+                        .iter()
+                        .all(|sid| after[*sid as usize] == to)
+                );
+            }
+            // the pinned and the restricted student are never moved
+            assert_eq!((after[0], after[1]), (0, 2));
+            evaluator.revert(undo);
+            assert_eq!(evaluator.assignment().classes(n_students), before);
+        }
+    }
+
+    #[test]
+    fn like_group_moves_only_if_the_group_fits() {
+        let mut bdata = grade();
+        let assign = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let mut evaluator = Evaluator::new(&bdata, &ctx, assign);
+        let to = evaluator.class_of(3);
+        evaluator.apply(Move::Student(4, (to + 1) % 3));
+        assert!(group_fits(&evaluator, &bdata, &[4], to));
+
+        // no seat left in the class of the group
+        let seats = evaluator.seats(to);
+        let mut full = bdata.clone();
+        full.class_configs[to as usize].capacity_max = Some(seats);
+        assert!(!group_fits(&evaluator, &full, &[4], to));
+        // student 1 may only be in class 2
+        assert!(!group_fits(&evaluator, &bdata, &[1], 0));
+    }
+}
@@ -0,0 +1,217 @@
+use super::{ClassRoom, StudentId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum RelationKind {
+    /// `from` should be in the same class as `to`
+    Together,
+    /// `from` must be separated from `to`
+    Apart,
+}
+
+impl RelationKind {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Self::Together => "with",
+            Self::Apart => "apart from",
+        }
+    }
+}
+
+/// dislike (separation) group, may be confidential like a school-violence record
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct SeparationGroup {
+    pub(crate) members: Vec<StudentId>,
+    /// hidden on screen unless revealed, left out of exports by default
+    pub(crate) confidential: bool,
+}
+
+impl SeparationGroup {
+    pub(crate) fn new(members: Vec<StudentId>) -> Self {
+        Self {
+            members,
+            confidential: false,
+        }
+    }
+}
+
+impl AsRef<[StudentId]> for SeparationGroup {
+    fn as_ref(&self) -> &[StudentId] {
+        &self.members
+    }
+}
+
+/// pairwise relationship between two students
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Relationship {
+    pub(crate) from: StudentId,
+    pub(crate) to: StudentId,
+    pub(crate) kind: RelationKind,
+    /// one-sided: only `from` asked for it (e.g. "C would prefer to be with D").
+    /// a mutual one is wanted by both, see [`Self::sides`]
+    pub(crate) directed: bool,
+    /// cost when the relationship is broken, for each student who wants it
+    pub(crate) weight: f32,
+    /// must never be broken (e.g. a bullying case)
+    pub(crate) hard: bool,
+    /// hidden on screen unless revealed, left out of exports by default
+    #[serde(default)]
+    pub(crate) confidential: bool,
+}
+
+impl Relationship {
+    pub(crate) fn new(from: StudentId, to: StudentId, kind: RelationKind) -> Self {
+        Self {
+            from,
+            to,
+            kind,
+            directed: false,
+            weight: 1000.0,
+            hard: false,
+            confidential: false,
+        }
+    }
+
+    /// every pair of every group as a mutual, soft relationship
+    pub(crate) fn cliques<G: AsRef<[StudentId]>>(groups: &[G], kind: RelationKind) -> Vec<Self> {
+        groups
+            .iter()
+            .flat_map(|group| Self::clique(group.as_ref(), kind))
+            .collect()
+    }
+
+    /// every pair of the group as a mutual, soft relationship
+    pub(crate) fn clique(
+        members: &[StudentId],
+        kind: RelationKind,
+    ) -> impl Iterator<Item = Self> + '_ {
+        members.iter().enumerate().flat_map(move |(i, &from)| {
+            members[i + 1..]
+                .iter()
+                .map(move |&to| Self::new(from, to, kind))
+        })
+    }
+
+    /// (student, other student) for each student who wants it: `from`, and `to` unless directed.
+    /// a broken soft relationship costs its weight once for each of them
+    pub(crate) fn sides(&self) -> impl Iterator<Item = (StudentId, StudentId)> {
+        std::iter::once((self.from, self.to))
+            .chain((!self.directed).then_some((self.to, self.from)))
+    }
+
+    /// cost of the soft relationship when broken, see [`Self::sides`]
+    pub(crate) fn broken_weight(&self) -> f32 {
+        self.weight * self.sides().count() as f32
+    }
+
+    /// true if the relationship is not honoured, `same_class` tells whether both are in one class
+    pub(crate) fn is_broken(&self, same_class: bool) -> bool {
+        match self.kind {
+            RelationKind::Together => !same_class,
+            RelationKind::Apart => same_class,
+        }
+    }
+}
+
+/// broken relationships of the students in one room
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub(crate) struct BrokenRelations {
+    pub(crate) count: usize,
+    /// hard ones are counted as violations, not as cost
+    pub(crate) hard: usize,
+    /// weights of the broken soft "together" relationships
+    pub(crate) together_cost: f32,
+    /// weights of the broken soft "apart" relationships
+    pub(crate) apart_cost: f32,
+}
+
+impl BrokenRelations {
+    /// adds (or removes) one side of the broken `rel`, see [`Relationship::sides`]:
+    /// the count and the hard violations go with the side of `from` only
+    pub(crate) fn record(&mut self, rel: &Relationship, from_side: bool, add: bool) {
+        let sign = if add { 1.0 } else { -1.0 };
+        if from_side {
+            let step = |n: &mut usize| if add { *n += 1 } else { *n -= 1 };
+            step(&mut self.count);
+            if rel.hard {
+                step(&mut self.hard);
+            }
+        }
+        match (rel.hard, rel.kind) {
+            (true, _) => {}
+            (false, RelationKind::Together) => self.together_cost += sign * rel.weight,
+            (false, RelationKind::Apart) => self.apart_cost += sign * rel.weight,
+        }
+    }
+}
+
+impl ClassRoom {
+    /// relationships of students in this room that are broken.
+    /// each side is counted in the room of its student, see [`BrokenRelations::record`]
+    pub(crate) fn count_broken_relations(&self, relations: &[Relationship]) -> BrokenRelations {
+        let mut broken = BrokenRelations::default();
+        for rel in relations.iter().filter(|rel| rel.from != rel.to) {
+            for (side, (own, other)) in rel.sides().enumerate() {
+                if self.contains(own) && rel.is_broken(self.contains(other)) {
+                    broken.record(rel, side == 0, true);
+                }
+            }
+        }
+        broken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{builder, room};
+    use super::super::{AssignResult, BuilderData, CostContext, Gender};
+    use super::*;
+
+    #[test]
+    fn broken_relationships_cost_their_weight() {
+        let bdata = builder(&[Gender::Male, Gender::Female, Gender::Female]);
+        let relations = vec![
+            Relationship {
+                hard: true,
+                ..Relationship::new(0, 1, RelationKind::Apart)
+            },
+            Relationship {
+                directed: true,
+                weight: 300.0,
+                ..Relationship::new(2, 0, RelationKind::Together)
+            },
+        ];
+
+        // a hard one is a violation, not a cost
+        let broken = room(&bdata, &[0, 1]).count_broken_relations(&relations);
+        assert_eq!((broken.count, broken.hard), (1, 1));
+        assert_eq!(broken.apart_cost, 0.0);
+
+        // one-sided: counted in the room of `from` only
+        let broken = room(&bdata, &[2]).count_broken_relations(&relations);
+        assert_eq!((broken.count, broken.hard), (1, 0));
+        assert_eq!(broken.together_cost, 300.0);
+    }
+
+    #[test]
+    fn one_sided_relationship_costs_less_than_a_mutual_one() {
+        let mut bdata = builder(&[Gender::Male, Gender::Female, Gender::Male, Gender::Female]);
+        let cost = |bdata: &BuilderData| {
+            let ctx = CostContext::new(bdata);
+            let mut assign = AssignResult::from_classes(bdata, &[0, 1, 0, 1]);
+            assign.cal_overall_cost(bdata, &ctx);
+            assign.overall_cost.unwrap()
+        };
+        let alone = cost(&bdata);
+
+        // 0 and 1 are in different classes: the relationship is broken
+        bdata.relationships = vec![Relationship::new(0, 1, RelationKind::Together)];
+        let mutual = cost(&bdata);
+        bdata.relationships[0].directed = true;
+        let directed = cost(&bdata);
+
+        let weight = 1000.0 * bdata.cost_weights.together;
+        assert_eq!(directed - alone, weight);
+        assert_eq!(mutual - alone, 2.0 * weight);
+    }
+}
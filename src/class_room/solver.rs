@@ -0,0 +1,433 @@
+use rand::{SeedableRng as _, rngs::StdRng};
+use web_time::Instant;
+
+use super::{
+    Annealing, AssignResult, BuilderData, CostContext, Exact, Population, TabuSearch, construct,
+    is_better,
+};
+
+/// the algorithms to choose from, see [`SolverKind::create`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum SolverKind {
+    /// `n_iteration` randomised greedy constructions, keeping the best
+    NaiveShuffle,
+    #[default]
+    Annealing,
+    TabuSearch,
+    Genetic,
+    /// integer program, small grades only: see [`ExactConfig`](super::ExactConfig)
+    Exact,
+}
+
+impl SolverKind {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::NaiveShuffle,
+        Self::Annealing,
+        Self::TabuSearch,
+        Self::Genetic,
+        Self::Exact,
+    ];
+
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Self::NaiveShuffle => "Naive Shuffle",
+            Self::Annealing => "Annealing",
+            Self::TabuSearch => "Tabu Search",
+            Self::Genetic => "Genetic",
+            Self::Exact => "Exact (ILP)",
+        }
+    }
+
+    /// the solver that actually runs on `bdata`, with a note if it is not `self`
+    pub(crate) fn resolve(self, bdata: &BuilderData) -> (Self, Option<String>) {
+        if self == Self::Exact && !bdata.exact.is_small_enough(bdata) {
+            let note = format!(
+                "{} students × {} classes is above the exact solver limit of {} variables: running tabu search instead",
+                bdata.students.len(),
+                bdata.n_class,
+                bdata.exact.max_variables,
+            );
+            return (Self::TabuSearch, Some(note));
+        }
+        (self, None)
+    }
+
+    /// a run starting from `initial`, with the settings stored in `bdata`
+    pub(crate) fn create<'a>(
+        self,
+        bdata: &'a BuilderData,
+        ctx: &'a CostContext,
+        initial: AssignResult,
+        rng: &mut StdRng,
+    ) -> Box<dyn Solver + 'a> {
+        match self {
+            Self::NaiveShuffle => Box::new(NaiveShuffle {
+                bdata,
+                ctx,
+                best: initial,
+            }),
+            Self::Annealing => Box::new(Annealing::new(
+                bdata,
+                ctx,
+                &bdata.annealing,
+                initial,
+                bdata.n_iteration,
+            )),
+            Self::TabuSearch => Box::new(TabuSearch::new(bdata, ctx, &bdata.tabu, initial)),
+            Self::Genetic => Box::new(Population::new(bdata, ctx, &bdata.genetic, &initial, rng)),
+            Self::Exact => Box::new(Exact::new(bdata, ctx, initial)),
+        }
+    }
+}
+
+/// one run of an algorithm. [`SolverRun`] calls `step` until the iteration budget
+/// is used up, the solver is done or the run is stopped, then takes the best.
+pub(crate) trait Solver: Send {
+    /// one unit of work, returns how many iterations it counts for
+    fn step(&mut self, rng: &mut StdRng) -> Result<usize, String>;
+
+    /// (hard violations, soft cost) of the best so far
+    fn best_objective(&self) -> (usize, f32);
+
+    /// current temperature, annealing only
+    fn temperature(&self) -> Option<f32> {
+        None
+    }
+
+    /// finished before the iteration budget is used up
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    /// best so far, not evaluated: see [`SolverRun::finish`]
+    fn into_best(self: Box<Self>) -> AssignResult;
+}
+
+/// repeated randomised constructions, see [`construct`]
+struct NaiveShuffle<'a> {
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    best: AssignResult,
+}
+
+impl Solver for NaiveShuffle<'_> {
+    fn step(&mut self, rng: &mut StdRng) -> Result<usize, String> {
+        let mut new = construct(self.bdata, self.ctx, rng);
+        new.cal_overall_cost(self.bdata, self.ctx);
+        if new.is_better_than(&self.best) {
+            self.best = new;
+        }
+        Ok(1)
+    }
+    fn best_objective(&self) -> (usize, f32) {
+        self.best.objective()
+    }
+    fn into_best(self: Box<Self>) -> AssignResult {
+        self.best
+    }
+}
+
+/// stopping criteria besides the iteration budget, stored with the project.
+/// a run stops at the first one met.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct StopConfig {
+    /// wall-clock budget in seconds: the result then depends on the machine, not only the seed
+    pub(crate) time_limit: Option<f32>,
+    /// iterations without a new best
+    pub(crate) no_improvement: Option<usize>,
+    /// soft cost to reach without hard violations
+    pub(crate) target_cost: Option<f32>,
+}
+
+/// progress of a run, as shown in the ui
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Progress {
+    pub(crate) step: usize,
+    pub(crate) total_step: usize,
+    /// share of the iteration or time budget used, whichever is larger
+    pub(crate) fraction: f32,
+    /// soft cost of the best so far
+    pub(crate) cost: f32,
+    /// current temperature, annealing only
+    pub(crate) temperature: Option<f32>,
+    /// seconds since the start
+    pub(crate) elapsed: f32,
+    /// iterations per second
+    pub(crate) rate: f32,
+    /// seconds left at most: the other criteria may stop the run earlier
+    pub(crate) eta: Option<f32>,
+}
+
+impl Progress {
+    pub(crate) fn new(step: usize, total_step: usize, cost: f32) -> Self {
+        Self {
+            step,
+            total_step,
+            fraction: step as f32 / total_step.max(1) as f32,
+            cost,
+            temperature: None,
+            elapsed: 0.0,
+            rate: 0.0,
+            eta: None,
+        }
+    }
+}
+
+/// number of progress reports over a whole run
+const N_REPORT: usize = 100;
+/// longest time between two progress reports, in seconds
+const REPORT_INTERVAL: f32 = 0.1;
+
+/// shared driver of all solvers: iteration budget, progress cadence and the final evaluation
+pub(crate) struct SolverRun<'a> {
+    kind: SolverKind,
+    bdata: &'a BuilderData,
+    ctx: &'a CostContext,
+    solver: Box<dyn Solver + 'a>,
+    rng: StdRng,
+    step: usize,
+    total_step: usize,
+    /// iterations between two progress reports
+    report_every: usize,
+    start: Instant,
+    best: (usize, f32),
+    /// step of the last new best
+    improved_at: usize,
+}
+
+impl<'a> SolverRun<'a> {
+    /// `kind` is run as is, see [`SolverKind::resolve`] for the fallback.
+    /// everything random comes from [`BuilderData::seed`], including the starting
+    /// assignment (the same as [`BuilderData::init`]): the current one is not used.
+    pub(crate) fn new(kind: SolverKind, bdata: &'a BuilderData, ctx: &'a CostContext) -> Self {
+        let mut rng = StdRng::seed_from_u64(bdata.seed);
+        let initial = bdata.initial_assignment(ctx, &mut rng);
+        let total_step = bdata.n_iteration.max(1);
+        Self {
+            kind,
+            bdata,
+            ctx,
+            solver: kind.create(bdata, ctx, initial, &mut rng),
+            rng,
+            step: 0,
+            total_step,
+            report_every: (total_step / N_REPORT).max(1),
+            start: Instant::now(),
+            best: (usize::MAX, f32::INFINITY),
+            improved_at: 0,
+        }
+    }
+
+    /// iteration budget used up, solver done, or one of the [`StopConfig`] criteria met
+    pub(crate) fn is_finished(&self) -> bool {
+        let stop = &self.bdata.stop;
+        self.step >= self.total_step
+            || self.solver.is_done()
+            || stop
+                .time_limit
+                .is_some_and(|limit| self.start.elapsed().as_secs_f32() >= limit)
+            || stop
+                .no_improvement
+                .is_some_and(|n| self.step - self.improved_at >= n)
+            || stop
+                .target_cost
+                .is_some_and(|target| self.best.0 == 0 && self.best.1 <= target)
+    }
+
+    pub(crate) fn progress(&self) -> Progress {
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let rate = self.step as f32 / elapsed.max(f32::EPSILON);
+        let step = self.step.min(self.total_step);
+        let mut fraction = step as f32 / self.total_step as f32;
+        let mut eta = (rate > 0.0).then(|| (self.total_step - step) as f32 / rate);
+        if let Some(limit) = self.bdata.stop.time_limit {
+            fraction = fraction.max(elapsed / limit.max(f32::EPSILON));
+            eta = Some(eta.map_or(limit, |eta| eta.min(limit - elapsed)));
+        }
+        Progress {
+            fraction: fraction.min(1.0),
+            temperature: self.solver.temperature(),
+            elapsed,
+            rate,
+            eta: eta.map(|eta| eta.max(0.0)),
+            ..Progress::new(step, self.total_step, self.solver.best_objective().1)
+        }
+    }
+
+    /// steps up to the next progress report: every 1% of the iterations, at least every 0.1 s
+    pub(crate) fn advance(&mut self) -> Result<Progress, String> {
+        let next = (self.step / self.report_every + 1) * self.report_every;
+        let started = Instant::now();
+        while self.step < next
+            && !self.is_finished()
+            && started.elapsed().as_secs_f32() < REPORT_INTERVAL
+        {
+            self.step += self.solver.step(&mut self.rng)?.max(1);
+            let best = self.solver.best_objective();
+            if is_better(best, self.best) {
+                self.best = best;
+                self.improved_at = self.step;
+            }
+        }
+        Ok(self.progress())
+    }
+
+    /// runs to the end, calling `report` after each [`Self::advance`]: it returns false to stop.
+    /// on the web, it yields to the ui between two reports.
+    pub(crate) async fn run(
+        mut self,
+        mut report: impl FnMut(Progress) -> bool,
+    ) -> Result<AssignResult, String> {
+        while !self.is_finished() {
+            #[cfg(target_arch = "wasm32")]
+            {
+                use gloo_timers::future::TimeoutFuture;
+                TimeoutFuture::new(0).await;
+            }
+            if !report(self.advance()?) {
+                break;
+            }
+        }
+        Ok(self.finish())
+    }
+
+    /// best of the run, fully evaluated
+    pub(crate) fn finish(self) -> AssignResult {
+        let mut best = self.solver.into_best();
+        best.seed = Some(self.bdata.seed);
+        best.solver = Some(self.kind);
+        best.cal_overall_cost(self.bdata, self.ctx);
+        best
+    }
+}
+
+/// runs `kind` on `bdata` from `seed` until the budget or a `stop` criterion ends it:
+/// the entry point of the ui and the tests. `report` gets the progress and returns false to cancel
+pub(crate) async fn solve(
+    bdata: &BuilderData,
+    kind: SolverKind,
+    seed: u64,
+    stop: &StopConfig,
+    report: impl FnMut(Progress) -> bool,
+) -> Result<AssignResult, String> {
+    let bdata = BuilderData {
+        seed,
+        stop: stop.clone(),
+        ..bdata.clone()
+    };
+    let ctx = CostContext::new(&bdata);
+    SolverRun::new(kind, &bdata, &ctx).run(report).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::super::fixture::grade;
+    use super::*;
+
+    /// [`solve`] with the settings of `bdata`, to the end: it never waits off the web
+    fn solve_to_end(kind: SolverKind, bdata: &BuilderData) -> Result<AssignResult, String> {
+        let (kind, _) = kind.resolve(bdata);
+        let mut run = pin!(solve(bdata, kind, bdata.seed, &bdata.stop, |_| true));
+        match run.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => unreachable!("solve waits only on the web"),
+        }
+    }
+
+    #[test]
+    fn every_solver_runs_below_100_iterations() {
+        let mut bdata = BuilderData {
+            n_iteration: 30,
+            ..grade()
+        };
+        bdata.init();
+        let initial = bdata.assign_result.clone().unwrap().objective();
+        for kind in SolverKind::ALL {
+            let best = solve_to_end(kind, &bdata).unwrap();
+            assert!(best.hard_violations.is_some(), "{}", kind.as_str());
+            assert!(!is_better(initial, best.objective()), "{}", kind.as_str());
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_assignment() {
+        let mut bdata = BuilderData {
+            n_iteration: 300,
+            seed: 7,
+            ..grade()
+        };
+        for kind in [
+            SolverKind::NaiveShuffle,
+            SolverKind::Annealing,
+            SolverKind::TabuSearch,
+            SolverKind::Genetic,
+        ] {
+            let first = solve_to_end(kind, &bdata).unwrap();
+            let again = solve_to_end(kind, &bdata).unwrap();
+            assert_eq!(first.seed, Some(7));
+            assert_eq!(first.solver, Some(kind));
+            assert_eq!(
+                first.classes(bdata.students.len()),
+                again.classes(bdata.students.len()),
+                "{}",
+                kind.as_str()
+            );
+            assert_eq!(first.objective(), again.objective());
+        }
+
+        bdata.init();
+        let start = bdata.assign_result.clone().unwrap();
+        bdata.seed = 8;
+        bdata.init();
+        assert_ne!(
+            start.classes(bdata.students.len()),
+            bdata.assign_result.unwrap().classes(bdata.students.len())
+        );
+    }
+
+    #[test]
+    fn runs_stop_at_the_first_criterion_met() {
+        let bdata = BuilderData {
+            n_iteration: 1_000_000,
+            ..grade()
+        };
+        let ctx = CostContext::new(&bdata);
+        let steps_until_stop = |stop: StopConfig| {
+            let bdata = BuilderData {
+                stop,
+                ..bdata.clone()
+            };
+            let mut run = SolverRun::new(SolverKind::Annealing, &bdata, &ctx);
+            let mut progress = run.progress();
+            while !run.is_finished() {
+                progress = run.advance().unwrap();
+            }
+            progress
+        };
+
+        let reached = steps_until_stop(StopConfig {
+            target_cost: Some(f32::MAX),
+            ..StopConfig::default()
+        });
+        assert_eq!(reached.step, 1);
+
+        let stalled = steps_until_stop(StopConfig {
+            no_improvement: Some(50),
+            ..StopConfig::default()
+        });
+        assert!(stalled.step < 1_000_000);
+
+        let timed = steps_until_stop(StopConfig {
+            time_limit: Some(0.05),
+            ..StopConfig::default()
+        });
+        assert!(timed.step < 1_000_000);
+        assert!(timed.elapsed >= 0.05);
+        assert_eq!(timed.fraction, 1.0);
+        assert_eq!(timed.eta, Some(0.0));
+    }
+}
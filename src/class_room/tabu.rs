@@ -0,0 +1,137 @@
+use rand::{Rng, rngs::StdRng};
+
+use super::{
+    AssignResult, BuilderData, CostContext, Evaluator, Move, Solver, is_better, sample_move,
+};
+
+/// tabu search settings, stored with the project
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct TabuConfig {
+    /// random candidate moves evaluated per iteration
+    pub(crate) neighbourhood: usize,
+    /// iterations a moved student may not move again
+    pub(crate) tenure: usize,
+}
+
+impl Default for TabuConfig {
+    fn default() -> Self {
+        Self {
+            neighbourhood: 50,
+            tenure: 20,
+        }
+    }
+}
+
+/// one tabu search run: each iteration takes the best non-tabu candidate, even if worse.
+/// a tabu candidate is taken anyway if it beats the best so far (aspiration).
+pub(crate) struct TabuSearch<'a> {
+    bdata: &'a BuilderData,
+    config: TabuConfig,
+    evaluator: Evaluator<'a>,
+    /// iteration until which each student is tabu, by id
+    tabu_until: Vec<usize>,
+    iteration: usize,
+    best: AssignResult,
+    best_objective: (usize, f32),
+}
+
+impl<'a> TabuSearch<'a> {
+    pub(crate) fn new(
+        bdata: &'a BuilderData,
+        ctx: &'a CostContext,
+        config: &TabuConfig,
+        initial: AssignResult,
+    ) -> Self {
+        let evaluator = Evaluator::new(bdata, ctx, initial);
+        Self {
+            bdata,
+            config: config.clone(),
+            best: evaluator.assignment().clone(),
+            best_objective: evaluator.objective(),
+            evaluator,
+            tabu_until: vec![0; bdata.students.len()],
+            iteration: 0,
+        }
+    }
+
+    /// one iteration, returns true if the best improved
+    pub(crate) fn step(&mut self, rng: &mut impl Rng) -> bool {
+        self.iteration += 1;
+
+        let mut chosen: Option<(Move, (usize, f32))> = None;
+        for _ in 0..self.config.neighbourhood {
+            let Some(mv) = sample_move(&self.evaluator, self.bdata, &self.bdata.moves, rng) else {
+                continue;
+            };
+            let undo = self.evaluator.apply(mv);
+            let objective = self.evaluator.objective();
+            self.evaluator.revert(undo);
+
+            let tabu = mv
+                .students(self.bdata)
+                .iter()
+                .any(|sid| self.tabu_until[*sid as usize] > self.iteration);
+            if tabu && !is_better(objective, self.best_objective) {
+                continue;
+            }
+            if chosen.is_none_or(|(_, chosen)| is_better(objective, chosen)) {
+                chosen = Some((mv, objective));
+            }
+        }
+
+        let Some((mv, objective)) = chosen else {
+            return false;
+        };
+        self.evaluator.apply(mv);
+        for sid in mv.students(self.bdata) {
+            self.tabu_until[sid as usize] = self.iteration + self.config.tenure;
+        }
+        if is_better(objective, self.best_objective) {
+            self.best_objective = objective;
+            self.best = self.evaluator.assignment().clone();
+            return true;
+        }
+        false
+    }
+}
+
+impl Solver for TabuSearch<'_> {
+    fn step(&mut self, rng: &mut StdRng) -> Result<usize, String> {
+        TabuSearch::step(self, rng);
+        Ok(1)
+    }
+    fn best_objective(&self) -> (usize, f32) {
+        self.best_objective
+    }
+    fn into_best(self: Box<Self>) -> AssignResult {
+        self.best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+
+    use super::super::fixture::{grade, started};
+    use super::*;
+
+    #[test]
+    fn tabu_search_never_loses_the_best() {
+        let mut bdata = grade();
+        let initial = started(&mut bdata);
+        let ctx = CostContext::new(&bdata);
+        let first = initial.objective();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let mut search = TabuSearch::new(&bdata, &ctx, &TabuConfig::default(), initial);
+        (0..50).for_each(|_| {
+            search.step(&mut rng);
+        });
+        let best_objective = Solver::best_objective(&search);
+        let mut best = Box::new(search).into_best();
+        best.cal_overall_cost(&bdata, &ctx);
+        assert!(!is_better(first, best.objective()));
+        assert_eq!(best.hard_violations, Some(best_objective.0));
+    }
+}